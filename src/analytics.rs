@@ -1,14 +1,106 @@
+use crate::categories::{category_ancestors, category_parts, is_within};
+use crate::query::Query;
+use crate::tags::has_tag;
 use crate::types::*;
-use chrono::{Datelike, Local};
-use std::collections::HashMap;
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 
 impl AppState {
+    /// Parses the date range filter; unparsable bounds are ignored.
+    pub fn filter_date_range(&self) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let parse = |s: &str| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok();
+        (parse(&self.filter_from), parse(&self.filter_to))
+    }
+
+    /// Indices into `data.transactions` of the transactions matching the search query and the
+    /// filter bar.
+    pub fn filtered_indices(&self) -> Vec<usize> {
+        let query = Query::parse(&self.search_term);
+        let (from, to) = self.filter_date_range();
+        self.data.transactions.iter().enumerate().filter(|(_, tx)| {
+            query.matches(tx, self.convert(tx.amount, tx.currency, self.base_currency))
+                && from.is_none_or(|from| tx.date >= from)
+                && to.is_none_or(|to| tx.date <= to)
+                && self.filter_category.as_ref().is_none_or(|c| tx.any_category(|cat| is_within(cat, c)))
+                && self.filter_currency.is_none_or(|c| tx.currency == c)
+                && self.filter_tag.as_ref().is_none_or(|t| has_tag(tx, t))
+        }).map(|(i, _)| i).collect()
+    }
+
+    /// Filtered indices in the order the table is sorted by.
+    pub fn sorted_indices(&self) -> Vec<usize> {
+        let mut indices = self.filtered_indices();
+        let Some(column) = self.sort_column else { return indices };
+        let txs = &self.data.transactions;
+        // Reverse the comparison rather than the result, so equal rows keep their order
+        let direction = |ordering: std::cmp::Ordering| if self.sort_ascending { ordering } else { ordering.reverse() };
+        let lowercase = |field: fn(&Transaction) -> &str| -> HashMap<usize, String> {
+            indices.iter().map(|&i| (i, field(&txs[i]).to_lowercase())).collect()
+        };
+        match column {
+            SortColumn::Date => indices.sort_by(|&a, &b| direction(txs[a].date.cmp(&txs[b].date))),
+            SortColumn::Description => {
+                let keys = lowercase(|t| &t.description);
+                indices.sort_by(|a, b| direction(keys[a].cmp(&keys[b])));
+            }
+            SortColumn::Category => {
+                let keys = lowercase(|t| &t.category);
+                indices.sort_by(|a, b| direction(keys[a].cmp(&keys[b])));
+            }
+            SortColumn::Amount => indices.sort_by(|&a, &b| direction(txs[a].amount.total_cmp(&txs[b].amount))),
+            SortColumn::Converted => {
+                let converted: HashMap<usize, f64> = indices
+                    .iter()
+                    .map(|&i| (i, self.convert(txs[i].amount, txs[i].currency, self.base_currency)))
+                    .collect();
+                indices.sort_by(|a, b| direction(converted[a].total_cmp(&converted[b])));
+            }
+        }
+        indices
+    }
+
+    fn rows_key(&self) -> RowsKey {
+        RowsKey {
+            data_version: self.data_version.get(),
+            search_term: self.search_term.clone(),
+            filter_from: self.filter_from.clone(),
+            filter_to: self.filter_to.clone(),
+            filter_category: self.filter_category.clone(),
+            filter_currency: self.filter_currency,
+            filter_tag: self.filter_tag.clone(),
+            sort_column: self.sort_column,
+            sort_ascending: self.sort_ascending,
+            base_currency: self.base_currency,
+        }
+    }
+
+    /// `sorted_indices`, rebuilt only after the transactions, filters or sort order change.
+    pub fn table_rows(&mut self) -> Rc<[usize]> {
+        let key = self.rows_key();
+        match &self.rows_cache {
+            Some((cached, rows)) if *cached == key => rows.clone(),
+            _ => {
+                let rows: Rc<[usize]> = self.sorted_indices().into();
+                self.rows_cache = Some((key, rows.clone()));
+                rows
+            }
+        }
+    }
+
+    /// Base-currency total of the table rows.
+    pub fn table_total(&mut self) -> f64 {
+        let rows = self.table_rows();
+        rows.iter()
+            .map(|&i| {
+                let t = &self.data.transactions[i];
+                self.convert(t.amount, t.currency, self.base_currency)
+            })
+            .sum()
+    }
+
     pub fn filtered_transactions(&self) -> Vec<&Transaction> {
-        self.data.transactions.iter().filter(|tx| {
-            self.search_term.is_empty() ||
-                tx.description.to_lowercase().contains(&self.search_term.to_lowercase()) ||
-                tx.category.to_lowercase().contains(&self.search_term.to_lowercase())
-        }).collect()
+        self.filtered_indices().into_iter().map(|i| &self.data.transactions[i]).collect()
     }
 
     pub fn total(&self) -> f64 {
@@ -18,22 +110,72 @@ impl AppState {
             .sum()
     }
 
-    pub fn category_sums_this_month(&self) -> HashMap<String, f64> {
-        let now = Local::now().naive_local();
-        let mut sums = HashMap::new();
-        for tx in &self.data.transactions {
-            if tx.date.year() == now.year() && tx.date.month() == now.month() {
-                let converted = self.convert(tx.amount, tx.currency, self.base_currency);
-                *sums.entry(tx.category.clone()).or_insert(0.0) += converted;
+    pub fn category_sums_for_month(&self, year: i32, month: u32) -> HashMap<String, f64> {
+        let (start, end) = BudgetPeriod::Monthly.containing(1, NaiveDate::from_ymd_opt(year, month, 1).unwrap());
+        self.category_sums_between(start, end)
+    }
+
+    /// Category sums in the base currency for every month that has transactions, oldest first.
+    pub fn category_sums_by_month(&self, transactions: &[&Transaction]) -> BTreeMap<(i32, u32), HashMap<String, f64>> {
+        let mut months: BTreeMap<(i32, u32), HashMap<String, f64>> = BTreeMap::new();
+        for tx in transactions {
+            let sums = months.entry((tx.date.year(), tx.date.month())).or_default();
+            for (category, amount) in tx.category_amounts() {
+                let converted = self.convert(amount, tx.currency, self.base_currency);
+                *sums.entry(category.to_string()).or_insert(0.0) += converted;
             }
         }
-        sums
+        months
     }
 
+    /// Every category in use or in the category list, with their parents, in tree order.
     pub fn categories(&self) -> Vec<String> {
-        let mut cats: Vec<String> = self.data.transactions.iter().map(|t| t.category.clone()).collect();
-        cats.sort();
+        let mut cats: Vec<String> = self
+            .data
+            .transactions
+            .iter()
+            .flat_map(|t| t.category_amounts().into_iter().map(|(category, _)| category))
+            .chain(self.data.categories.iter().map(String::as_str))
+            .flat_map(category_ancestors)
+            .collect();
+        cats.sort_by(|a, b| category_parts(a).cmp(&category_parts(b)));
         cats.dedup();
         cats
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tx(day: u32, description: &str, amount: f64) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2026, 9, day).unwrap(),
+            description: description.to_string(),
+            amount,
+            category: "Food".to_string(),
+            currency: Currency::USD,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn table_rows_follow_filters_sorting_and_saves() {
+        let mut state = AppState::load_or_default(None);
+        state.data.transactions = vec![tx(3, "Bakery", 5.0), tx(1, "Grocer", 40.0), tx(2, "Bakery", 7.0)];
+        assert_eq!(&*state.table_rows(), &[0, 1, 2]);
+
+        state.sort_column = Some(SortColumn::Date);
+        state.sort_ascending = true;
+        assert_eq!(&*state.table_rows(), &[1, 2, 0]);
+        state.search_term = "bakery".to_string();
+        assert_eq!(&*state.table_rows(), &[2, 0]);
+        assert_eq!(state.table_total(), 12.0);
+
+        // Edits only show up once they are saved
+        state.data.transactions.push(tx(4, "Bakery", 3.0));
+        assert_eq!(&*state.table_rows(), &[2, 0]);
+        state.save();
+        assert_eq!(&*state.table_rows(), &[2, 0, 3]);
+        assert_eq!(state.table_total(), 15.0);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::categories::rollup;
+use crate::types::*;
+use chrono::NaiveDate;
+
+impl Budget {
+    /// Every limit `category` has had, oldest first.
+    pub fn versions(&self, category: &str) -> Vec<&CategoryBudget> {
+        self.history.get(category).into_iter().flatten().chain(self.limits.get(category)).collect()
+    }
+
+    /// The limit of `category` in force on `date`.
+    pub fn limit_on(&self, category: &str, date: NaiveDate) -> Option<&CategoryBudget> {
+        self.versions(category).into_iter().rev().find(|l| l.effective.is_none_or(|d| d <= date))
+    }
+
+    /// Makes `limit` the limit of `category` from the start of the period containing
+    /// `effective`, keeping earlier limits for earlier periods. A category's first limit
+    /// applies to the past too. Limits from the same period onwards are replaced.
+    pub fn set_limit(&mut self, category: &str, mut limit: CategoryBudget, effective: NaiveDate) {
+        let Some(previous) = self.limits.remove(category) else {
+            self.limits.insert(category.to_string(), limit);
+            return;
+        };
+        let effective = limit.period.containing(limit.start_day, effective).0;
+        limit.effective = Some(effective);
+        let history = self.history.entry(category.to_string()).or_default();
+        history.push(previous);
+        history.retain(|l| l.effective.is_none_or(|d| d < effective));
+        if history.is_empty() {
+            // Nothing left to fall back on before `effective`
+            limit.effective = None;
+            self.history.remove(category);
+        }
+        self.limits.insert(category.to_string(), limit);
+    }
+}
+
+/// Budget and spending of one category in one month, in the base currency.
+pub struct MonthBudget {
+    pub category: String,
+    pub budget: Option<f64>,
+    pub actual: f64,
+}
+
+impl AppState {
+    /// Every budgeted category's spending, including subcategories, in the given months
+    /// against the limit in force at the start of each month. Limits for other periods are
+    /// spread over an average month.
+    pub fn budget_vs_actual(&self, months: &[(i32, u32)]) -> Vec<((i32, u32), Vec<MonthBudget>)> {
+        let mut categories: Vec<&String> = self.data.budget.limits.keys().collect();
+        categories.sort();
+        months
+            .iter()
+            .filter_map(|&(year, month)| Some(((year, month), NaiveDate::from_ymd_opt(year, month, 1)?)))
+            .map(|(key, first)| {
+                let sums = rollup(&self.category_sums_for_month(key.0, key.1));
+                let row = categories
+                    .iter()
+                    .map(|&category| {
+                        let budget = self
+                            .data
+                            .budget
+                            .limit_on(category, first)
+                            .map(|l| self.convert(l.monthly_amount(), l.currency, self.base_currency));
+                        let actual = sums.get(category).copied().unwrap_or(0.0);
+                        MonthBudget { category: category.clone(), budget, actual }
+                    })
+                    .collect();
+                (key, row)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,85 @@
+use crate::tags::{has_tag, normalize_tag};
+use crate::types::*;
+
+impl AppState {
+    /// Updates the selection for a click on transaction `i`. `rows` is the table order, used to
+    /// select the range between the previously clicked row and this one.
+    pub fn click_row(&mut self, i: usize, rows: &[usize], toggle: bool, range: bool) {
+        let anchor = self.selected_tx.and_then(|a| rows.iter().position(|&r| r == a));
+        let clicked = rows.iter().position(|&r| r == i);
+        if let (true, Some(a), Some(c)) = (range, anchor, clicked) {
+            if !toggle {
+                self.selection.clear();
+            }
+            self.selection.extend(&rows[a.min(c)..=a.max(c)]);
+            // Keep the anchor so the range can be adjusted with another shift-click
+            return;
+        }
+        if toggle {
+            if !self.selection.remove(&i) {
+                self.selection.insert(i);
+            }
+        } else {
+            self.selection.clear();
+            self.selection.insert(i);
+        }
+        self.selected_tx = Some(i);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+        self.selected_tx = None;
+        self.split_tx = None;
+    }
+
+    /// Removes every selected transaction and returns how many were deleted.
+    pub fn delete_selected(&mut self) -> usize {
+        let count = self.selection.len();
+        // Highest index first so the remaining indices stay valid
+        for &i in self.selection.iter().rev() {
+            if i < self.data.transactions.len() {
+                self.data.transactions.remove(i);
+            }
+        }
+        self.clear_selection();
+        count
+    }
+
+    fn update_selected(&mut self, mut update: impl FnMut(&mut Transaction)) -> usize {
+        let mut count = 0;
+        for &i in &self.selection {
+            if let Some(tx) = self.data.transactions.get_mut(i) {
+                update(tx);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Moves the selected transactions to `category`, undoing any splits.
+    pub fn set_category_selected(&mut self, category: &str) -> usize {
+        self.update_selected(|tx| {
+            tx.category = category.to_string();
+            tx.splits.clear();
+        })
+    }
+
+    /// Relabels the selected transactions; amounts are not converted.
+    pub fn set_currency_selected(&mut self, currency: Currency) -> usize {
+        self.update_selected(|tx| tx.currency = currency)
+    }
+
+    pub fn set_recurring_selected(&mut self, recurring: bool) -> usize {
+        self.update_selected(|tx| tx.recurring = recurring)
+    }
+
+    /// Adds `tag` to the selected transactions that don't have it yet.
+    pub fn tag_selected(&mut self, tag: &str) -> usize {
+        let tag = normalize_tag(tag);
+        self.update_selected(|tx| {
+            if !has_tag(tx, &tag) {
+                tx.tags.push(tag.clone());
+            }
+        })
+    }
+}
@@ -0,0 +1,207 @@
+use crate::types::*;
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use std::str::FromStr;
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |node, name| child(node, name))
+}
+
+fn text(node: Option<Node>) -> Option<String> {
+    node.and_then(|n| n.text()).map(|t| t.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+/// Parses an ISO 20022 CAMT.053 bank-to-customer statement. Only booked entries are read;
+/// batch entries with several transaction details become one transaction per detail.
+pub fn parse_camt053(content: &str) -> Result<ImportReport, roxmltree::Error> {
+    let doc = Document::parse(content)?;
+    let mut report = ImportReport::default();
+
+    for stmt in doc.descendants().filter(|n| n.has_tag_name("Stmt")) {
+        let acct = child(stmt, "Acct");
+        let account = acct
+            .and_then(|a| child(a, "Id"))
+            .and_then(|id| text(child(id, "IBAN")).or_else(|| text(path(id, &["Othr", "Id"]))))
+            .unwrap_or_default();
+
+        for entry in stmt.children().filter(|n| n.has_tag_name("Ntry")) {
+            let status = text(child(entry, "Sts")).or_else(|| text(path(entry, &["Sts", "Cd"])));
+            if status.as_deref().is_some_and(|s| s != "BOOK") {
+                continue;
+            }
+            let line = doc.text_pos_at(entry.range().start).row as usize;
+            let booked = child(entry, "BookgDt")
+                .and_then(|d| text(child(d, "Dt")).or_else(|| text(child(d, "DtTm"))));
+            let date = booked.as_deref().and_then(|d| d.get(..10)).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            let Some(date) = date else {
+                report.issues.push(ImportIssue {
+                    line,
+                    field: "BookgDt".into(),
+                    reason: format!("missing or invalid booking date '{}'", booked.unwrap_or_default()),
+                });
+                continue;
+            };
+            let entry_debit = text(child(entry, "CdtDbtInd")).as_deref() != Some("CRDT");
+            let entry_ref = text(child(entry, "AcctSvcrRef"));
+            let entry_info = text(child(entry, "AddtlNtryInf"));
+
+            let details: Vec<Node> = entry
+                .children()
+                .filter(|n| n.has_tag_name("NtryDtls"))
+                .flat_map(|d| d.children().filter(|n| n.has_tag_name("TxDtls")))
+                .collect();
+            let batch = details.len() > 1
+                && details.iter().all(|d| path(*d, &["AmtDtls", "TxAmt", "Amt"]).or_else(|| child(*d, "Amt")).is_some());
+
+            let mut parts: Vec<(Node, Option<Node>)> = Vec::new();
+            if batch {
+                for d in &details {
+                    let amt = path(*d, &["AmtDtls", "TxAmt", "Amt"]).or_else(|| child(*d, "Amt"));
+                    parts.push((amt.unwrap_or(entry), Some(*d)));
+                }
+            } else if let Some(amt) = child(entry, "Amt") {
+                parts.push((amt, details.first().copied()));
+            } else {
+                report.issues.push(ImportIssue { line, field: "Amt".into(), reason: "missing amount".into() });
+                continue;
+            }
+
+            for (amt, detail) in parts {
+                let raw = amt.text().unwrap_or("").trim();
+                let Ok(amount) = raw.parse::<f64>() else {
+                    report.issues.push(ImportIssue { line, field: "Amt".into(), reason: format!("'{raw}' is not a number") });
+                    continue;
+                };
+                let ccy = amt.attribute("Ccy").unwrap_or("");
+                let Ok(currency) = Currency::from_str(ccy) else {
+                    report.issues.push(ImportIssue { line, field: "Ccy".into(), reason: format!("unsupported currency '{ccy}'") });
+                    continue;
+                };
+                let debit = detail
+                    .and_then(|d| text(child(d, "CdtDbtInd")))
+                    .map(|ind| ind != "CRDT")
+                    .unwrap_or(entry_debit);
+
+                let remittance: Vec<String> = detail
+                    .and_then(|d| child(d, "RmtInf"))
+                    .map(|r| r.children().filter(|n| n.has_tag_name("Ustrd")).filter_map(|n| text(Some(n))).collect())
+                    .unwrap_or_default();
+                // The counterparty is the creditor for outgoing payments and the debtor for incoming ones
+                let party = detail.and_then(|d| {
+                    let role = if debit { "Cdtr" } else { "Dbtr" };
+                    text(path(d, &["RltdPties", role, "Nm"])).or_else(|| text(path(d, &["RltdPties", role, "Pty", "Nm"])))
+                });
+                let description = if !remittance.is_empty() {
+                    remittance.join(" ")
+                } else {
+                    party.or(entry_info.clone()).unwrap_or_default()
+                };
+                let external_id = detail
+                    .and_then(|d| text(path(d, &["Refs", "AcctSvcrRef"])))
+                    .or_else(|| if batch { None } else { entry_ref.clone() })
+                    .or_else(|| detail.and_then(|d| text(path(d, &["Refs", "EndToEndId"]))).filter(|id| id != "NOTPROVIDED"));
+
+                report.rows.push(ImportRow::new(Transaction {
+                    date,
+                    description,
+                    amount: if debit { amount } else { -amount },
+                    category: "Uncategorized".to_string(),
+                    currency,
+                    account: account.clone(),
+                    external_id,
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">250.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2026-09-20</Dt></BookgDt>
+        <AcctSvcrRef>BATCH1</AcctSvcrRef>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><AcctSvcrRef>B1-1</AcctSvcrRef></Refs>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">100.00</Amt></TxAmt></AmtDtls>
+            <RltdPties><Cdtr><Nm>Power Co</Nm></Cdtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <Refs><AcctSvcrRef>B1-2</AcctSvcrRef></Refs>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">150.00</Amt></TxAmt></AmtDtls>
+            <RmtInf><Ustrd>Water</Ustrd><Ustrd>Q3</Ustrd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1800.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><DtTm>2026-09-25T08:00:00</DtTm></BookgDt>
+        <AcctSvcrRef>SAL9</AcctSvcrRef>
+        <NtryDtls><TxDtls><RltdPties><Dbtr><Nm>Employer AG</Nm></Dbtr></RltdPties></TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">5.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <BookgDt><Dt>2026-09-26</Dt></BookgDt>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn batch_entries_and_signs() {
+        let report = parse_camt053(STATEMENT).unwrap();
+        assert!(report.issues.is_empty());
+        let rows: Vec<(&str, f64, Option<&str>)> = report
+            .rows
+            .iter()
+            .map(|r| (r.transaction.description.as_str(), r.transaction.amount, r.transaction.external_id.as_deref()))
+            .collect();
+        assert_eq!(
+            rows,
+            [("Power Co", 100.0, Some("B1-1")), ("Water Q3", 150.0, Some("B1-2")), ("Employer AG", -1800.0, Some("SAL9"))]
+        );
+        let salary = &report.rows[2].transaction;
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2026, 9, 25).unwrap());
+        assert_eq!((salary.account.as_str(), salary.currency), ("DE89370400440532013000", Currency::EUR));
+    }
+
+    #[test]
+    fn detail_indicator_overrides_the_entry() {
+        let content = STATEMENT.replace(
+            "<Refs><AcctSvcrRef>B1-2</AcctSvcrRef></Refs>",
+            "<Refs><AcctSvcrRef>B1-2</AcctSvcrRef></Refs><CdtDbtInd>CRDT</CdtDbtInd>",
+        );
+        let report = parse_camt053(&content).unwrap();
+        assert_eq!(report.rows[1].transaction.amount, -150.0);
+    }
+
+    #[test]
+    fn reports_bad_entries() {
+        let content = STATEMENT.replace("<Dt>2026-09-20</Dt>", "<Dt>20.09.2026</Dt>").replace("1800.00", "lots");
+        let report = parse_camt053(&content).unwrap();
+        let fields: Vec<&str> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, ["BookgDt", "Amt"]);
+        assert!(report.rows.is_empty());
+        assert!(parse_camt053("<Document>").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::types::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+/// Separates the levels of a category path, e.g. `Food > Groceries`.
+pub const CATEGORY_SEPARATOR: &str = " > ";
+
+/// The levels of a category path, trimmed.
+pub fn category_parts(category: &str) -> Vec<&str> {
+    category.split('>').map(str::trim).filter(|p| !p.is_empty()).collect()
+}
+
+/// Canonical spelling of a category path: `Food>Groceries` becomes `Food > Groceries`.
+pub fn normalize_category(category: &str) -> String {
+    category_parts(category).join(CATEGORY_SEPARATOR)
+}
+
+/// The category and all of its parents, outermost first: `Food`, `Food > Groceries`.
+pub fn category_ancestors(category: &str) -> Vec<String> {
+    let parts = category_parts(category);
+    (1..=parts.len()).map(|n| parts[..n].join(CATEGORY_SEPARATOR)).collect()
+}
+
+/// Whether `category` is `ancestor` or one of its subcategories.
+pub fn is_within(category: &str, ancestor: &str) -> bool {
+    category == ancestor
+        || category.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with(CATEGORY_SEPARATOR))
+}
+
+/// The last level of a category path, for display in an indented tree.
+pub fn category_leaf(category: &str) -> &str {
+    category.rsplit(CATEGORY_SEPARATOR).next().unwrap_or(category)
+}
+
+pub fn category_depth(category: &str) -> usize {
+    category.matches(CATEGORY_SEPARATOR).count()
+}
+
+/// Adds every category's amount to all of its parents, so `Food` includes `Food > Groceries`.
+pub fn rollup(sums: &HashMap<String, f64>) -> HashMap<String, f64> {
+    let mut rolled = HashMap::new();
+    for (category, amount) in sums {
+        for ancestor in category_ancestors(category) {
+            *rolled.entry(ancestor).or_insert(0.0) += amount;
+        }
+    }
+    rolled
+}
+
+/// `category` with the `from` prefix replaced by `to`, if it is within `from`.
+fn renamed(category: &str, from: &str, to: &str) -> Option<String> {
+    is_within(category, from).then(|| format!("{to}{}", &category[from.len()..]))
+}
+
+impl AppState {
+    /// Transaction count and total in the base currency per category, including subcategories.
+    pub fn category_stats(&self) -> HashMap<String, (usize, f64)> {
+        let mut stats: HashMap<String, (usize, f64)> = HashMap::new();
+        for tx in &self.data.transactions {
+            let mut counted = HashSet::new();
+            for (category, amount) in tx.category_amounts() {
+                let converted = self.convert(amount, tx.currency, self.base_currency);
+                for ancestor in category_ancestors(category) {
+                    let entry = stats.entry(ancestor.clone()).or_default();
+                    // Several split lines in one category still count as one transaction
+                    if counted.insert(ancestor) {
+                        entry.0 += 1;
+                    }
+                    entry.1 += converted;
+                }
+            }
+        }
+        stats
+    }
+
+    /// Renames `from` and its subcategories to `to` in every transaction, budget, rule and the
+    /// category list. Renaming onto an existing category merges them; the target's budget is
+    /// kept if both have one. Returns the number of transactions changed.
+    pub fn rename_category(&mut self, from: &str, to: &str) -> usize {
+        let to = normalize_category(to);
+        if from == to || to.is_empty() || is_within(&to, from) {
+            return 0;
+        }
+        let mut changed = 0;
+        for tx in &mut self.data.transactions {
+            let mut touched = false;
+            if let Some(name) = renamed(&tx.category, from, &to) {
+                tx.category = name;
+                touched = true;
+            }
+            for split in &mut tx.splits {
+                if let Some(name) = renamed(&split.category, from, &to) {
+                    split.category = name;
+                    touched = true;
+                }
+            }
+            changed += touched as usize;
+        }
+        let limits = std::mem::take(&mut self.data.budget.limits);
+        let (moved, mut kept): (HashMap<_, _>, HashMap<_, _>) =
+            limits.into_iter().partition(|(category, _)| is_within(category, from));
+        let mut history = Vec::new();
+        for (category, limit) in moved {
+            let name = renamed(&category, from, &to).unwrap_or(category.clone());
+            // Earlier limits go with the limit that is kept
+            if let Entry::Vacant(entry) = kept.entry(name.clone()) {
+                if let Some(versions) = self.data.budget.history.remove(&category) {
+                    history.push((name, versions));
+                }
+                entry.insert(limit);
+            }
+        }
+        self.data.budget.limits = kept;
+        self.data.budget.history.retain(|c, _| !is_within(c, from));
+        self.data.budget.history.extend(history);
+        for assignment in &mut self.data.budget.assignments {
+            if let Some(name) = renamed(&assignment.category, from, &to) {
+                assignment.category = name;
+            }
+        }
+        for goal in &mut self.data.goals {
+            if let Some(name) = renamed(&goal.category, from, &to) {
+                goal.category = name;
+            }
+        }
+        for loan in &mut self.data.loans {
+            if let Some(name) = renamed(&loan.category, from, &to) {
+                loan.category = name;
+            }
+        }
+        for rule in &mut self.data.rules {
+            if let Some(name) = renamed(&rule.category, from, &to) {
+                rule.category = name;
+            }
+        }
+        for category in &mut self.data.categories {
+            if let Some(name) = renamed(category, from, &to) {
+                *category = name;
+            }
+        }
+        self.data.categories.sort();
+        self.data.categories.dedup();
+        let styles = std::mem::take(&mut self.data.category_styles);
+        let (moved, mut kept): (HashMap<_, _>, HashMap<_, _>) =
+            styles.into_iter().partition(|(category, _)| is_within(category, from));
+        for (category, style) in moved {
+            let name = renamed(&category, from, &to).unwrap_or(category);
+            kept.entry(name).or_insert(style);
+        }
+        self.data.category_styles = kept;
+        if let Some(filter) = &self.filter_category {
+            self.filter_category = renamed(filter, from, &to).or(self.filter_category.take());
+        }
+        changed
+    }
+
+    /// Deletes `category` and its subcategories, moving their transactions to `reassign_to`
+    /// (Uncategorized if empty), along with the money in their envelopes and the rules that
+    /// assign them. Their budgets and styles are dropped. Returns the number of transactions
+    /// moved.
+    pub fn delete_category(&mut self, category: &str, reassign_to: &str) -> usize {
+        let target = normalize_category(reassign_to);
+        let target = if target.is_empty() { "Uncategorized".to_string() } else { target };
+        if is_within(&target, category) {
+            return 0;
+        }
+        let mut moved = 0;
+        for tx in &mut self.data.transactions {
+            let mut touched = false;
+            if is_within(&tx.category, category) {
+                tx.category = target.clone();
+                touched = true;
+            }
+            for split in tx.splits.iter_mut().filter(|s| is_within(&s.category, category)) {
+                split.category = target.clone();
+                touched = true;
+            }
+            moved += touched as usize;
+        }
+        self.data.budget.limits.retain(|c, _| !is_within(c, category));
+        self.data.budget.history.retain(|c, _| !is_within(c, category));
+        // Money in the deleted envelopes follows their transactions
+        for assignment in self.data.budget.assignments.iter_mut().filter(|a| is_within(&a.category, category)) {
+            assignment.category = target.clone();
+        }
+        for goal in self.data.goals.iter_mut().filter(|g| is_within(&g.category, category)) {
+            goal.category = target.clone();
+        }
+        for loan in self.data.loans.iter_mut().filter(|l| is_within(&l.category, category)) {
+            loan.category = target.clone();
+        }
+        for rule in self.data.rules.iter_mut().filter(|r| is_within(&r.category, category)) {
+            rule.category = target.clone();
+        }
+        self.data.category_styles.retain(|c, _| !is_within(c, category));
+        self.data.categories.retain(|c| !is_within(c, category));
+        if self.filter_category.as_deref().is_some_and(|f| is_within(f, category)) {
+            self.filter_category = None;
+        }
+        moved
+    }
+
+    /// Adds an empty category to the category list so it can be budgeted before it is used.
+    pub fn add_category(&mut self, name: &str) -> Option<String> {
+        let name = normalize_category(name);
+        if name.is_empty() || self.data.categories.contains(&name) {
+            return None;
+        }
+        self.data.categories.push(name.clone());
+        self.data.categories.sort();
+        Some(name)
+    }
+
+    /// The style set on `category` or, failing that, on its nearest parent.
+    pub fn category_style(&self, category: &str) -> Option<&CategoryStyle> {
+        category_ancestors(category).iter().rev().find_map(|c| self.data.category_styles.get(c))
+    }
+}
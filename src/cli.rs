@@ -0,0 +1,800 @@
+use crate::categories::{category_depth, category_leaf, normalize_category};
+use crate::query::Query;
+use crate::tags::normalize_tag;
+use crate::reports::parse_month;
+use crate::types::*;
+use chrono::{Datelike, NaiveDate};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const USAGE: &str = "Usage:
+  fintrack                                         start the GUI
+  fintrack import <profile.json> <file> [--format csv|ofx|qif|xml|mt940|journal] [--skip-flagged]
+                                                   import a CSV, OFX/QFX, QIF, CAMT.053, MT940 or ledger file
+  fintrack list <profile.json> [QUERY...] [--base USD]
+                                                   list transactions matching a search query
+  fintrack categories <profile.json> [--base USD]   show the category tree with counts and totals
+  fintrack rename-category <profile.json> <FROM> <TO>
+                                                   rename a category and its subcategories, merging
+                                                   into TO if it already exists
+  fintrack tags <profile.json> [QUERY...]         totals per tag for transactions matching a query
+  fintrack suggest <profile.json> <DESCRIPTION>    likely categories for a description, learned from history
+  fintrack apply-rules <profile.json>              re-run the categorisation rules over all transactions
+  fintrack budgets <profile.json> [--date YYYY-MM-DD] [--base USD] [--history]
+                                                   spending against each budget in its current period,
+                                                   with --history every period amounts were carried over
+  fintrack set-budget <profile.json> <CATEGORY> <AMOUNT> [--currency EUR]
+                 [--period weekly|bi-weekly|monthly|quarterly|yearly] [--start DAY]
+                 [--rollover none|unspent|overspent|both] [--cap AMOUNT] [--since YYYY-MM-DD]
+                 [--from YYYY-MM-DD]
+                                                   set a category budget from the period containing
+                                                   --from (today by default); DAY is the day of the month
+                                                   the period starts on, or of the week (1 = Monday)
+  fintrack envelopes <profile.json> [--on|--off]   envelope balances and money available to assign; --on and
+                                                   --off switch envelope budgeting
+  fintrack assign <profile.json> <CATEGORY> <AMOUNT>
+                                                   put income into an envelope (negative to take it out)
+  fintrack move-envelope <profile.json> <FROM> <TO> <AMOUNT>
+                                                   move money between envelopes
+  fintrack goals <profile.json>                    progress of each savings goal
+  fintrack add-goal <profile.json> <NAME> <TARGET> [--currency EUR] [--by YYYY-MM-DD]
+                 [--category NAME] [--account NAME] [--tag TAG] [--saved AMOUNT]
+                                                   add a savings goal, or replace the one with that name
+  fintrack loans <profile.json> [NAME] [--schedule] [--split-payments]
+                                                   balance and payoff date of each loan; --schedule prints
+                                                   the amortization schedule, --split-payments splits
+                                                   payments into principal and interest
+  fintrack add-loan <profile.json> <NAME> <PRINCIPAL> <RATE%> <MONTHS> <FIRST-PAYMENT>
+                 [--currency EUR] [--category NAME]
+                                                   add a loan; payments are transactions in the category
+                                                   (Loans > NAME by default)
+  fintrack forecast <profile.json> [--months 3] [--date YYYY-MM-DD] [--balance ACCOUNT=AMOUNT]
+                                                   project account balances from recurring and scheduled
+                                                   transactions, loans and budgets; --balance sets an
+                                                   account's current balance in the base currency
+  fintrack budget-history <profile.json> [YYYY-MM] [--months 12] [--base USD]
+                                                   spending against the budget in force each month,
+                                                   for the months up to YYYY-MM
+  fintrack export <profile.json> <out> [--format csv|qif|journal|xlsx] [--converted] [--base USD]
+                 [--query QUERY] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--category NAME] [--currency EUR]
+                 [--tag TAG]
+                                                   export transactions, optionally only those matching the filters
+  fintrack report <profile.json> <YYYY-MM> <out.pdf> [--base USD]
+                                                   write the monthly statement PDF";
+
+/// Runs a command-line invocation. Running `fintrack` with no arguments starts the GUI instead.
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("import") => import(&args[1..]),
+        Some("list") => list(&args[1..]),
+        Some("categories") => categories(&args[1..]),
+        Some("rename-category") => rename_category(&args[1..]),
+        Some("tags") => tags(&args[1..]),
+        Some("suggest") => suggest(&args[1..]),
+        Some("apply-rules") => apply_rules(&args[1..]),
+        Some("budgets") => budgets(&args[1..]),
+        Some("set-budget") => set_budget(&args[1..]),
+        Some("budget-history") => budget_history(&args[1..]),
+        Some("envelopes") => envelopes(&args[1..]),
+        Some("goals") => goals(&args[1..]),
+        Some("loans") => loans(&args[1..]),
+        Some("add-loan") => add_loan(&args[1..]),
+        Some("add-goal") => add_goal(&args[1..]),
+        Some("forecast") => forecast(&args[1..]),
+        Some("assign") => assign(&args[1..]),
+        Some("move-envelope") => move_envelope(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("report") => report(&args[1..]),
+        Some("help") | Some("--help") | Some("-h") => {
+            println!("{USAGE}\n\nQueries: {}", crate::query::HELP);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn import(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut skip_flagged = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--skip-flagged" => skip_flagged = true,
+            "--format" => {
+                let name = iter.next().ok_or("--format needs a value")?;
+                format = Some(ImportFormat::from_name(name).ok_or(format!("unknown import format '{name}'"))?);
+            }
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    let [profile, file] = positional.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let format = format
+        .or_else(|| ImportFormat::from_path(file))
+        .ok_or("cannot tell the file format from its extension; pass --format")?;
+
+    let mut state = AppState::load_profile(profile.clone(), true)?;
+    let (report, added) = state
+        .import_file(format, file, skip_flagged)
+        .map_err(|e| format!("{} import failed: {e}", format.label()))?;
+
+    for issue in &report.issues {
+        eprintln!("line {}: {} — {}", issue.line, issue.field, issue.reason);
+    }
+    for row in report.rows.iter().filter(|r| r.duplicate_of.is_some()) {
+        let tx = &row.transaction;
+        eprintln!("suspected duplicate: {} {} {:.2} {}", tx.date, tx.description, tx.amount, tx.currency);
+    }
+    if report.needs_review() && !skip_flagged {
+        return Err(format!(
+            "nothing imported: {} flagged rows, {} suspected duplicates (re-run with --skip-flagged to import the rest)",
+            report.flagged_rows(),
+            report.duplicates()
+        ));
+    }
+    state.save();
+    println!(
+        "imported {} transactions, skipped {} flagged rows and {} duplicates",
+        added,
+        report.flagged_rows(),
+        report.duplicates()
+    );
+    Ok(())
+}
+
+fn parse_currency(code: Option<&String>) -> Result<Currency, String> {
+    let code = code.ok_or("expected a currency code")?;
+    Currency::from_str(&code.to_uppercase()).map_err(|_| format!("unsupported currency '{code}'"))
+}
+
+fn check_query(query: &str) -> Result<(), String> {
+    let errors = Query::parse(query).errors;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("invalid query: {}", errors.join("; ")))
+    }
+}
+
+fn list(args: &[String]) -> Result<(), String> {
+    let mut terms = Vec::new();
+    let mut base = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--base" => base = Some(parse_currency(iter.next())?),
+            _ => terms.push(arg.as_str()),
+        }
+    }
+    let Some((profile, terms)) = terms.split_first() else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    state.search_term = terms.join(" ");
+    check_query(&state.search_term)?;
+    for tx in state.filtered_transactions() {
+        println!(
+            "{}  {:>10.2} {}  {:>10.2} {}  {:<20}  {}",
+            tx.date,
+            tx.amount,
+            tx.currency,
+            state.convert(tx.amount, tx.currency, state.base_currency),
+            state.base_currency,
+            tx.category_label(),
+            tx.description
+        );
+    }
+    println!("{} transactions, total {:.2} {}", state.filtered_transactions().len(), state.total(), state.base_currency);
+    Ok(())
+}
+
+fn categories(args: &[String]) -> Result<(), String> {
+    let (profile, base) = match args {
+        [profile] => (profile, None),
+        [profile, flag, base] if flag == "--base" => (profile, Some(parse_currency(Some(base))?)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    let stats = state.category_stats();
+    for category in state.categories() {
+        let (count, total) = stats.get(&category).copied().unwrap_or_default();
+        let name = format!("{}{}", "  ".repeat(category_depth(&category)), category_leaf(&category));
+        println!("{name:<32} {count:>6} {total:>12.2} {}", state.base_currency);
+    }
+    Ok(())
+}
+
+fn rename_category(args: &[String]) -> Result<(), String> {
+    let [profile, from, to] = args else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let from = normalize_category(from);
+    if !state.categories().contains(&from) {
+        return Err(format!("no category '{from}'"));
+    }
+    let changed = state.rename_category(&from, to);
+    state.save();
+    println!("renamed '{from}' to '{}' in {changed} transactions", normalize_category(to));
+    Ok(())
+}
+
+fn tags(args: &[String]) -> Result<(), String> {
+    let Some((profile, query)) = args.split_first() else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    state.search_term = query.join(" ");
+    check_query(&state.search_term)?;
+    let totals = state.tag_totals(&state.filtered_transactions());
+    for (tag, (count, total)) in totals {
+        println!("#{tag:<24} {count:>6} {total:>12.2} {}", state.base_currency);
+    }
+    Ok(())
+}
+
+fn suggest(args: &[String]) -> Result<(), String> {
+    let [profile, description @ ..] = args else {
+        return Err(USAGE.to_string());
+    };
+    let state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let suggestions = state.suggest_categories(&description.join(" "));
+    if suggestions.is_empty() {
+        return Err("no similar transactions to learn from".to_string());
+    }
+    for (category, probability) in suggestions {
+        println!("{:>5.1}%  {category}", probability * 100.0);
+    }
+    Ok(())
+}
+
+fn apply_rules(args: &[String]) -> Result<(), String> {
+    let [profile] = args else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let changed = state.reapply_rules();
+    state.save();
+    println!("{} rules updated {changed} transactions", state.data.rules.len());
+    Ok(())
+}
+
+fn budgets(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut date = chrono::Local::now().date_naive();
+    let mut base = None;
+    let mut history = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--history" => history = true,
+            "--date" => {
+                let value = iter.next().ok_or("--date needs a value")?;
+                date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{value}'"))?;
+            }
+            "--base" => base = Some(parse_currency(iter.next())?),
+            _ => positional.push(arg),
+        }
+    }
+    let [profile] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    let mut categories: Vec<&String> = state.data.budget.limits.keys().collect();
+    categories.sort();
+    for category in categories {
+        let limit = &state.data.budget.limits[category];
+        let periods = state.budget_history(category, date);
+        let Some(progress) = periods.last() else { continue };
+        let last = progress.end.pred_opt().unwrap_or(progress.end);
+        let carried = if progress.carried.abs() >= 0.005 { format!("  carried {:.2}", progress.carried) } else { String::new() };
+        println!(
+            "{category:<24} {:<22} {} – {}  {:>10.2} of {:>10.2} {}{carried}{}",
+            limit.period_label(),
+            progress.start,
+            last,
+            progress.spent,
+            progress.allowance(),
+            state.base_currency,
+            if progress.remaining() < 0.0 { "  over" } else { "" }
+        );
+        if history && limit.rollover.is_enabled() {
+            for period in &periods {
+                println!(
+                    "    {}  budget {:>10.2}  carried {:>10.2}  spent {:>10.2}  left {:>10.2}",
+                    period.start,
+                    period.budget,
+                    period.carried,
+                    period.spent,
+                    period.remaining()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn set_budget(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut currency = None;
+    let mut period = BudgetPeriod::Monthly;
+    let mut start_day = 1;
+    let mut rollover = None;
+    let mut cap = None;
+    let mut since = None;
+    let mut effective = chrono::Local::now().date_naive();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--rollover" => {
+                let value = iter.next().ok_or("--rollover needs a value")?;
+                rollover = Some(match value.to_lowercase().as_str() {
+                    "none" => (false, false),
+                    "unspent" => (true, false),
+                    "overspent" => (false, true),
+                    "both" => (true, true),
+                    _ => return Err(format!("unknown rollover '{value}'")),
+                });
+            }
+            "--cap" => {
+                let value = iter.next().ok_or("--cap needs a value")?;
+                let value = value.parse::<f64>().map_err(|_| format!("invalid cap '{value}'"))?;
+                if !value.is_finite() || value < 0.0 {
+                    return Err("the cap must be zero or more".to_string());
+                }
+                cap = Some(value);
+            }
+            "--from" => {
+                let value = iter.next().ok_or("--from needs a value")?;
+                effective = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{value}'"))?;
+            }
+            "--since" => {
+                let value = iter.next().ok_or("--since needs a value")?;
+                since = Some(NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{value}'"))?);
+            }
+            "--currency" => currency = Some(parse_currency(iter.next())?),
+            "--period" => {
+                let value = iter.next().ok_or("--period needs a value")?;
+                period = BudgetPeriod::from_str(value).map_err(|_| format!("unknown period '{value}'"))?;
+            }
+            "--start" => {
+                let value = iter.next().ok_or("--start needs a value")?;
+                start_day = value.parse().map_err(|_| format!("invalid start day '{value}'"))?;
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [profile, category, amount] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let amount: f64 = amount.parse().map_err(|_| format!("invalid amount '{amount}'"))?;
+    if !amount.is_finite() || amount < 0.0 {
+        return Err("the budget must be zero or more".to_string());
+    }
+    if !(1..=period.max_start_day()).contains(&start_day) {
+        return Err(format!("a {} budget starts on day 1 to {}", period.as_str().to_lowercase(), period.max_start_day()));
+    }
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let category = normalize_category(category);
+    let currency = currency.unwrap_or(state.base_currency);
+    // Keep the rollover settings of an existing budget unless they are given
+    let mut rollover_settings = state.data.budget.limits.get(&category).map(|l| l.rollover.clone()).unwrap_or_default();
+    if let Some((unspent, overspent)) = rollover {
+        rollover_settings.unspent = unspent;
+        rollover_settings.overspent = overspent;
+    }
+    rollover_settings.cap = cap.or(rollover_settings.cap);
+    rollover_settings.since = since.or(rollover_settings.since);
+    let limit = CategoryBudget { amount, currency, period, start_day, rollover: rollover_settings, effective: None };
+    state.data.budget.set_limit(&category, limit, effective);
+    let limit = &state.data.budget.limits[&category];
+    match limit.effective {
+        Some(from) => println!("{category}: {amount:.2} {currency}, {} from {from}", limit.period_label()),
+        None => println!("{category}: {amount:.2} {currency}, {}", limit.period_label()),
+    }
+    state.save();
+    Ok(())
+}
+
+fn budget_history(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut count = 12;
+    let mut base = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--months" => {
+                let value = iter.next().ok_or("--months needs a value")?;
+                count = value.parse::<i32>().map_err(|_| format!("invalid number of months '{value}'"))?;
+            }
+            "--base" => base = Some(parse_currency(iter.next())?),
+            _ => positional.push(arg),
+        }
+    }
+    let (profile, (year, month)) = match positional[..] {
+        [profile] => {
+            let today = chrono::Local::now().date_naive();
+            (profile, (today.year(), today.month()))
+        }
+        [profile, month] => (profile, parse_month(month).ok_or(format!("invalid month '{month}', expected YYYY-MM"))?),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    let last = year * 12 + month as i32 - 1;
+    let months: Vec<(i32, u32)> = (last + 1 - count.max(1)..=last)
+        .map(|index| (index.div_euclid(12), index.rem_euclid(12) as u32 + 1))
+        .collect();
+    for ((year, month), row) in state.budget_vs_actual(&months) {
+        for cell in row {
+            let budget = cell.budget.map_or("-".to_string(), |b| format!("{b:.2}"));
+            let over = if cell.budget.is_some_and(|b| cell.actual > b) { "  over" } else { "" };
+            println!("{year}-{month:02}  {:<24} {:>10.2} of {budget:>10} {}{over}", cell.category, cell.actual, state.base_currency);
+        }
+    }
+    Ok(())
+}
+
+fn envelopes(args: &[String]) -> Result<(), String> {
+    let (profile, mode) = match args {
+        [profile] => (profile, None),
+        [profile, flag] if flag == "--on" => (profile, Some(true)),
+        [profile, flag] if flag == "--off" => (profile, Some(false)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(mode) = mode {
+        state.data.budget.envelope_mode = mode;
+        state.save();
+        println!("envelope budgeting is {}", if mode { "on" } else { "off" });
+    }
+    let summary = state.envelope_summary();
+    let base = state.base_currency;
+    for (envelope, (assigned, spent)) in &summary.envelopes {
+        println!("{envelope:<24} assigned {assigned:>10.2}  spent {spent:>10.2}  balance {:>10.2} {base}", assigned - spent);
+    }
+    println!("income {:.2}, assigned {:.2}, spent outside envelopes {:.2} {base}", summary.income, summary.assigned, summary.unbudgeted);
+    println!("available to assign: {:.2} {base}", summary.available());
+    Ok(())
+}
+
+/// Loads a profile for an envelope command, which only make sense with envelope budgeting on.
+fn envelope_state(profile: &str) -> Result<AppState, String> {
+    let state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if !state.data.budget.envelope_mode {
+        return Err(format!("envelope budgeting is off; turn it on with `fintrack envelopes {profile} --on`"));
+    }
+    Ok(state)
+}
+
+fn assign(args: &[String]) -> Result<(), String> {
+    let [profile, category, amount] = args else {
+        return Err(USAGE.to_string());
+    };
+    let amount: f64 = amount.parse().map_err(|_| format!("invalid amount '{amount}'"))?;
+    let mut state = envelope_state(profile)?;
+    let category = normalize_category(category);
+    state.assign_to_envelope(&category, amount)?;
+    state.save();
+    println!("{category}: {:.2} {}", state.envelope_summary().balance(&category), state.base_currency);
+    Ok(())
+}
+
+fn move_envelope(args: &[String]) -> Result<(), String> {
+    let [profile, from, to, amount] = args else {
+        return Err(USAGE.to_string());
+    };
+    let amount: f64 = amount.parse().map_err(|_| format!("invalid amount '{amount}'"))?;
+    let mut state = envelope_state(profile)?;
+    let (from, to) = (normalize_category(from), normalize_category(to));
+    state.move_between_envelopes(&from, &to, amount)?;
+    state.save();
+    let summary = state.envelope_summary();
+    println!("{from}: {:.2}, {to}: {:.2} {}", summary.balance(&from), summary.balance(&to), state.base_currency);
+    Ok(())
+}
+
+fn goals(args: &[String]) -> Result<(), String> {
+    let [profile] = args else {
+        return Err(USAGE.to_string());
+    };
+    let state = AppState::load_profile(PathBuf::from(profile), false)?;
+    for goal in &state.data.goals {
+        let progress = state.goal_progress(goal);
+        let by = goal.target_date.map_or(String::new(), |d| format!(" by {d}"));
+        println!(
+            "{}: {:.2} of {:.2} {}{by} ({:.0}%)",
+            goal.name,
+            progress.saved,
+            goal.target,
+            goal.currency,
+            progress.fraction(goal) * 100.0
+        );
+        if progress.is_reached(goal) {
+            continue;
+        }
+        let projected = progress.projected.map_or("never at this pace".to_string(), |d| d.to_string());
+        println!("    saving {:.2} a month, reached {projected}", progress.monthly_rate);
+        if let Some(needed) = progress.monthly_needed {
+            println!("    needs {needed:.2} a month to finish on time");
+        }
+    }
+    Ok(())
+}
+
+fn add_goal(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut currency = None;
+    let mut target_date = None;
+    let (mut category, mut account, mut tag) = (String::new(), String::new(), String::new());
+    let mut initial = 0.0;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--currency" => currency = Some(parse_currency(iter.next())?),
+            "--by" | "--category" | "--account" | "--tag" | "--saved" => {
+                let value = iter.next().ok_or(format!("{arg} needs a value"))?;
+                match arg.as_str() {
+                    "--by" => {
+                        target_date = Some(NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{value}'"))?)
+                    }
+                    "--category" => category = normalize_category(value),
+                    "--account" => account = value.trim().to_string(),
+                    "--tag" => tag = normalize_tag(value),
+                    _ => initial = value.parse().map_err(|_| format!("invalid amount '{value}'"))?,
+                }
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [profile, name, target] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let target: f64 = target.parse().map_err(|_| format!("invalid amount '{target}'"))?;
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let currency = currency.unwrap_or(state.base_currency);
+    let goal = SavingsGoal { name: name.trim().to_string(), target, currency, target_date, category, account, tag, initial };
+    goal.validate()?;
+    match state.data.goals.iter_mut().find(|g| g.name.eq_ignore_ascii_case(&goal.name)) {
+        Some(existing) => *existing = goal,
+        None => state.data.goals.push(goal),
+    }
+    state.save();
+    println!("saved goal '{}'", name.trim());
+    Ok(())
+}
+
+fn loans(args: &[String]) -> Result<(), String> {
+    let (flags, positional): (Vec<&String>, Vec<&String>) = args.iter().partition(|a| a.starts_with("--"));
+    let (schedule, split) = (flags.iter().any(|f| *f == "--schedule"), flags.iter().any(|f| *f == "--split-payments"));
+    if let Some(flag) = flags.iter().find(|f| !["--schedule", "--split-payments"].contains(&f.as_str())) {
+        return Err(format!("unknown option {flag}"));
+    }
+    let (profile, name) = match positional[..] {
+        [profile] => (profile, None),
+        [profile, name] => (profile, Some(name)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let loans: Vec<Loan> = state
+        .data
+        .loans
+        .iter()
+        .filter(|l| name.is_none_or(|n| l.name.eq_ignore_ascii_case(n)))
+        .cloned()
+        .collect();
+    if let (Some(name), true) = (name, loans.is_empty()) {
+        return Err(format!("no loan named '{name}'"));
+    }
+    if split {
+        for loan in &loans {
+            println!("split {} payment(s) of {}", state.split_loan_payments(loan), loan.name);
+        }
+        state.save();
+    }
+    for loan in &loans {
+        let status = state.loan_status(loan);
+        let currency = loan.currency;
+        println!("{}: {:.2} {currency} at {}% over {} months, {:.2} a month", loan.name, loan.principal, loan.rate, loan.term_months, loan.payment());
+        println!("    paid {:.2} principal and {:.2} interest, {:.2} {currency} left", status.paid_principal, status.paid_interest, status.balance);
+        match status.payoff {
+            Some(date) => println!("    paid off {date}"),
+            None => println!("    the installment doesn't cover the interest"),
+        }
+        if schedule {
+            for row in loan.schedule() {
+                println!(
+                    "    {:>4}  {}  {:>10.2}  interest {:>10.2}  principal {:>10.2}  balance {:>12.2}",
+                    row.number, row.date, row.payment, row.interest, row.principal, row.balance
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn add_loan(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut currency = None;
+    let mut category = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--currency" => currency = Some(parse_currency(iter.next())?),
+            "--category" => category = Some(normalize_category(iter.next().ok_or("--category needs a value")?)),
+            _ => positional.push(arg),
+        }
+    }
+    let [profile, name, principal, rate, months, start] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let number = |s: &str| s.trim_end_matches('%').parse::<f64>().map_err(|_| format!("invalid number '{s}'"));
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    let name = name.trim().to_string();
+    let loan = Loan {
+        principal: number(principal)?,
+        rate: number(rate)?,
+        term_months: months.parse().map_err(|_| format!("invalid number of months '{months}'"))?,
+        currency: currency.unwrap_or(state.base_currency),
+        start: NaiveDate::parse_from_str(start, "%Y-%m-%d").map_err(|_| format!("invalid date '{start}'"))?,
+        category: category.unwrap_or_else(|| normalize_category(&format!("Loans > {name}"))),
+        name,
+    };
+    loan.validate()?;
+    println!("{}: {:.2} {} a month, payments in {}", loan.name, loan.payment(), loan.currency, loan.category);
+    state.data.loans.push(loan);
+    state.save();
+    Ok(())
+}
+
+fn forecast(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut months = 3;
+    let mut date = chrono::Local::now().date_naive();
+    let mut balances = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--months" => {
+                let value = iter.next().ok_or("--months needs a value")?;
+                months = value.parse().ok().filter(|&m| m > 0).ok_or(format!("invalid number of months '{value}'"))?;
+            }
+            "--date" => {
+                let value = iter.next().ok_or("--date needs a value")?;
+                date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("invalid date '{value}'"))?;
+            }
+            "--balance" => {
+                let value = iter.next().ok_or("--balance needs a value")?;
+                let (account, amount) = value.rsplit_once('=').ok_or(format!("expected ACCOUNT=AMOUNT, got '{value}'"))?;
+                let amount = amount.trim().parse::<f64>().map_err(|_| format!("invalid amount '{amount}'"))?;
+                if !amount.is_finite() {
+                    return Err("the balance must be a number".to_string());
+                }
+                balances.push((account.trim().to_string(), amount));
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [profile] = positional[..] else {
+        return Err(USAGE.to_string());
+    };
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if !balances.is_empty() {
+        state.data.account_balances.extend(balances);
+        state.save();
+    }
+    let forecast = state.forecast(date, months);
+    let base = state.base_currency;
+    println!("Forecast {} – {} in {base}", forecast.start, forecast.end);
+    for item in &forecast.items {
+        println!("  {}  {:<10} {:<16} {:<32} {:>10.2}", item.date, item.source.as_str(), item.account, item.description, -item.amount);
+    }
+    for (account, points) in &forecast.balances {
+        let start = points.first().map_or(0.0, |p| p.1);
+        let end = points.last().map_or(0.0, |p| p.1);
+        let lowest = points.iter().map(|p| p.1).fold(start, f64::min);
+        println!("{account:<16} now {start:>10.2}  on {} {end:>10.2}  lowest {lowest:>10.2}", forecast.end);
+    }
+    for (account, date, balance) in forecast.warnings() {
+        println!("warning: {account} would go negative on {date} ({balance:.2} {base})");
+    }
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut format = None;
+    let mut base = None;
+    let mut options = ExportOptions::default();
+    let mut filters: Vec<(&str, String)> = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => format = Some(iter.next().ok_or("--format needs a value")?.to_lowercase()),
+            "--converted" => options.include_converted = true,
+            "--base" => base = Some(parse_currency(iter.next())?),
+            "--query" | "--from" | "--to" | "--category" | "--currency" | "--tag" => {
+                let value = iter.next().ok_or(format!("{arg} needs a value"))?;
+                filters.push((arg.as_str(), value.clone()));
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let [profile, out] = positional.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let out = PathBuf::from(out);
+    let format = format
+        .or_else(|| out.extension().and_then(|e| e.to_str()).map(str::to_lowercase))
+        .ok_or("cannot tell the export format from the file name; pass --format")?;
+
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    options.filtered = !filters.is_empty();
+    for (flag, value) in filters {
+        match flag {
+            "--query" => state.search_term = value,
+            "--from" => state.filter_from = value,
+            "--to" => state.filter_to = value,
+            "--category" => state.filter_category = Some(value),
+            "--tag" => state.filter_tag = Some(normalize_tag(&value)),
+            _ => state.filter_currency = Some(parse_currency(Some(&value))?),
+        }
+    }
+    check_query(&state.search_term)?;
+    let (from, to) = state.filter_date_range();
+    if (from.is_none() && !state.filter_from.is_empty()) || (to.is_none() && !state.filter_to.is_empty()) {
+        return Err("dates must be YYYY-MM-DD".to_string());
+    }
+
+    let result = match format.as_str() {
+        "csv" => state.export_csv(&out, &options),
+        "qif" => state.export_qif(&out, &options),
+        "journal" | "ledger" | "hledger" => state.export_ledger(&out, &options),
+        "xlsx" => state.export_xlsx(&out, &options),
+        _ => return Err(format!("unknown export format '{format}'")),
+    };
+    result.map_err(|e| format!("export failed: {e}"))?;
+    println!("exported {} transactions to {}", state.export_transactions(&options).len(), out.display());
+    Ok(())
+}
+
+fn report(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+    let mut base = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--base" => base = Some(parse_currency(iter.next())?),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let [profile, month, out] = positional.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let (year, month) = parse_month(month).ok_or(format!("'{month}' is not a YYYY-MM month"))?;
+    let mut state = AppState::load_profile(PathBuf::from(profile), false)?;
+    if let Some(base) = base {
+        state.base_currency = base;
+    }
+    state
+        .export_monthly_pdf(year, month, &PathBuf::from(out))
+        .map_err(|e| format!("PDF export failed: {e}"))?;
+    println!("wrote {out}");
+    Ok(())
+}
@@ -1,9 +1,12 @@
+use crate::categories::{normalize_category, CATEGORY_SEPARATOR};
+use crate::tags::parse_tags;
 use crate::types::*;
 use std::fs;
 use std::path::PathBuf;
 use chrono::{Local, NaiveDate};
 use csv::{ReaderBuilder, WriterBuilder};
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr; // <----- ADD THIS LINE
 
 pub fn fetch_exchange_rates_api(base: Currency, supported: &[Currency]) -> Result<HashMap<(Currency, Currency), f64>, String> {
@@ -41,7 +44,21 @@ impl AppState {
         } else {
             BudgetAppData::default()
         };
+        Self::with_data(data, file_path)
+    }
+
+    /// Loads the profile at `path`, failing instead of starting over when it can't be read
+    /// or parsed. A missing file starts an empty profile only when `create` is set.
+    pub fn load_profile(path: PathBuf, create: bool) -> Result<Self, String> {
+        let data = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{} is not a valid profile: {e}", path.display()))?,
+            Err(e) if create && e.kind() == std::io::ErrorKind::NotFound => BudgetAppData::default(),
+            Err(e) => return Err(format!("cannot read profile {}: {e}", path.display())),
+        };
+        Ok(Self::with_data(data, Some(path)))
+    }
 
+    fn with_data(data: BudgetAppData, file_path: Option<PathBuf>) -> Self {
         let mut exchange_rates = HashMap::new();
         // Example rates
         exchange_rates.insert((Currency::USD, Currency::USD), 1.0);
@@ -62,20 +79,89 @@ impl AppState {
             input_date_str: Local::now().date_naive().to_string(),
             input_recurring: false,
             input_currency: Currency::USD,
+            input_tags: String::new(),
             search_term: String::new(),
+            filter_from: String::new(),
+            filter_to: String::new(),
+            filter_category: None,
+            filter_currency: None,
+            filter_tag: None,
+            export_options: ExportOptions::default(),
+            sort_column: None,
+            sort_ascending: true,
             file_path,
             selected_tx: None,
+            selection: BTreeSet::new(),
+            bulk_category: String::new(),
+            bulk_currency: Currency::USD,
+            bulk_tag: String::new(),
+            tag_input: String::new(),
+            split_tx: None,
+            split_lines: Vec::new(),
+            show_category_manager: false,
+            category_editing: None,
+            category_name_input: String::new(),
+            category_target: None,
+            new_category_input: String::new(),
+            suggestions_for: String::new(),
+            category_suggestions: Vec::new(),
+            description_completions: Vec::new(),
+            show_rules: false,
+            show_budget_history: false,
+            envelope_category: String::new(),
+            envelope_amount: String::new(),
+            envelope_move_to: None,
+            rule_pattern: String::new(),
+            rule_regex: false,
+            rule_min: String::new(),
+            rule_max: String::new(),
+            rule_currency: None,
+            rule_account: String::new(),
+            rule_category: String::new(),
+            rule_tags: String::new(),
+            show_goals: false,
+            goal_editing: None,
+            goal_name: String::new(),
+            goal_target: String::new(),
+            goal_currency: Currency::USD,
+            goal_date: String::new(),
+            goal_category: String::new(),
+            goal_account: String::new(),
+            goal_tag: String::new(),
+            goal_initial: String::new(),
+            show_loans: false,
+            loan_schedule: None,
+            loan_name: String::new(),
+            loan_principal: String::new(),
+            loan_rate: String::new(),
+            loan_term: String::new(),
+            loan_currency: Currency::USD,
+            loan_start: String::new(),
+            loan_category: String::new(),
+            show_forecast: false,
+            forecast_months: 3,
             theme: Theme::Light,
             show_import_modal: false,
             import_path: None,
+            pending_import: None,
+            report_month: Local::now().format("%Y-%m").to_string(),
             base_currency: Currency::USD,
             exchange_rates,
             editing_rates: false,
             rates_api_error: None,
+            data_version: Cell::new(0),
+            rows_cache: None,
+            budget_cache: None,
         }
     }
 
+    /// Marks the transactions, budgets or rates as changed, so cached views are rebuilt.
+    pub fn invalidate_views(&self) {
+        self.data_version.set(self.data_version.get() + 1);
+    }
+
     pub fn save(&self) {
+        self.invalidate_views();
         if let Some(path) = &self.file_path {
             if let Ok(json) = serde_json::to_string_pretty(&self.data) {
                 let _ = fs::write(path, json);
@@ -85,9 +171,11 @@ impl AppState {
 
     pub fn load(&mut self, file_path: PathBuf) {
         self.file_path = Some(file_path.clone());
+        self.clear_selection();
         if let Ok(content) = fs::read_to_string(&file_path) {
             self.data = serde_json::from_str(&content).unwrap_or_default();
         }
+        self.invalidate_views();
     }
 
     pub fn convert(&self, amount: f64, from: Currency, to: Currency) -> f64 {
@@ -114,37 +202,577 @@ impl AppState {
         }
     }
 
-    pub fn export_csv(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    /// The transactions an export should contain: everything, or only what the active
+    /// search and filters show.
+    pub fn export_transactions(&self, options: &ExportOptions) -> Vec<&Transaction> {
+        if options.selection {
+            self.selection.iter().filter_map(|&i| self.data.transactions.get(i)).collect()
+        } else if options.filtered {
+            self.filtered_transactions()
+        } else {
+            self.data.transactions.iter().collect()
+        }
+    }
+
+    pub fn export_csv(&self, path: &PathBuf, options: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
         let mut wtr = WriterBuilder::new().from_path(path)?;
-        wtr.write_record(&["date", "description", "amount", "currency", "category", "recurring"])?;
-        for tx in &self.data.transactions {
-            wtr.write_record(&[
+        let mut header = vec!["date".to_string(), "description".into(), "amount".into(), "currency".into(), "category".into(), "recurring".into(), "tags".into()];
+        if options.include_converted {
+            header.push(format!("amount_{}", self.base_currency));
+        }
+        wtr.write_record(&header)?;
+        for tx in self.export_transactions(options) {
+            let mut record = vec![
                 tx.date.to_string(),
                 tx.description.clone(),
                 tx.amount.to_string(),
                 tx.currency.as_str().to_string(),
                 tx.category.clone(),
-                tx.recurring.to_string()
-            ])?;
+                tx.recurring.to_string(),
+                tx.tags.join(" "),
+            ];
+            if options.include_converted {
+                record.push(format!("{:.2}", self.convert(tx.amount, tx.currency, self.base_currency)));
+            }
+            wtr.write_record(&record)?;
         }
         wtr.flush()?;
         Ok(())
     }
 
-    pub fn import_csv(&mut self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let mut rdr = ReaderBuilder::new().from_path(path)?;
-        for result in rdr.records() {
-            let record = result?;
-            let date = chrono::NaiveDate::parse_from_str(&record[0], "%Y-%m-%d")?;
-            let description = record[1].to_string();
-            let amount: f64 = record[2].parse()?;
-            let currency = Currency::all().iter().find(|c| c.as_str() == &record[3]).copied().unwrap_or(Currency::USD);
-            let category = record[4].to_string();
-            let recurring: bool = record[5].parse()?;
-            self.data.transactions.push(Transaction {
-                date, description, amount, category, recurring, currency
-            });
+    /// Writes transactions as a QIF bank register. QIF has no currency field, so amounts are
+    /// converted to the base currency; transactions with an account get their own `!Account` block.
+    pub fn export_qif(&self, path: &PathBuf, options: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
+        let transactions = self.export_transactions(options);
+        let mut accounts: Vec<&str> = transactions.iter().map(|t| t.account.as_str()).collect();
+        accounts.sort();
+        accounts.dedup();
+        let mut out = String::new();
+        for account in accounts {
+            if !account.is_empty() {
+                out.push_str(&format!("!Account\nN{account}\nTBank\n^\n"));
+            }
+            out.push_str("!Type:Bank\n");
+            for tx in transactions.iter().filter(|t| t.account == account) {
+                let amount = self.convert(tx.amount, tx.currency, self.base_currency);
+                out.push_str(&format!("D{}\n", tx.date.format("%m/%d/%Y")));
+                // QIF amounts are negative for withdrawals, the opposite of ours
+                out.push_str(&format!("T{:.2}\n", -amount));
+                out.push_str(&format!("P{}\n", tx.description));
+                if !tx.category.is_empty() {
+                    out.push_str(&format!("L{}\n", tx.category.replace(CATEGORY_SEPARATOR, ":")));
+                }
+                for split in &tx.splits {
+                    out.push_str(&format!("S{}\n", split.category.replace(CATEGORY_SEPARATOR, ":")));
+                    if !split.memo.is_empty() {
+                        out.push_str(&format!("E{}\n", split.memo));
+                    }
+                    let amount = self.convert(split.amount, tx.currency, self.base_currency);
+                    out.push_str(&format!("${:.2}\n", -amount));
+                }
+                out.push_str("^\n");
+            }
         }
+        fs::write(path, out)?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Parses and validates an import file and flags rows that duplicate existing transactions.
+    /// Rows are only added when nothing needs review, or when `skip_invalid` is set, in which
+    /// case flagged rows are dropped and suspected duplicates get their default action. Returns
+    /// the report and how many transactions were added.
+    pub fn import_file(&mut self, format: ImportFormat, path: &PathBuf, skip_invalid: bool) -> Result<(ImportReport, usize), Box<dyn std::error::Error>> {
+        let mut report = parse_import(format, path, self.base_currency)?;
+        self.apply_rules_to_report(&mut report);
+        self.mark_duplicates(&mut report);
+        let added = if !report.needs_review() || skip_invalid { self.apply_import(&report) } else { 0 };
+        Ok((report, added))
+    }
+
+    /// Applies the valid rows of a report according to their action and returns how many
+    /// transactions were added. A row to merge whose duplicate has since been deleted is added.
+    pub fn apply_import(&mut self, report: &ImportReport) -> usize {
+        let mut added = 0;
+        for row in &report.rows {
+            match (row.action, row.duplicate_in(&self.data.transactions)) {
+                (DuplicateAction::Skip, _) => {}
+                (DuplicateAction::Merge, Some(idx)) => {
+                    let existing = &mut self.data.transactions[idx];
+                    let category = std::mem::take(&mut existing.category);
+                    let splits = std::mem::take(&mut existing.splits);
+                    let recurring = existing.recurring;
+                    *existing = row.transaction.clone();
+                    if existing.category.is_empty() {
+                        existing.category = category;
+                    }
+                    // Keep splits made by hand unless the import brings its own
+                    if existing.splits.is_empty() && (splits.iter().map(|s| s.amount).sum::<f64>() - existing.amount).abs() < 0.005 {
+                        existing.splits = splits;
+                    }
+                    existing.recurring |= recurring;
+                }
+                _ => {
+                    self.data.transactions.push(row.transaction.clone());
+                    added += 1;
+                }
+            }
+        }
+        added
+    }
+
+    /// Flags rows that look like an existing transaction: same currency and amount, dates within
+    /// a few days and similar descriptions. Each existing transaction matches at most one row.
+    pub fn mark_duplicates(&self, report: &mut ImportReport) {
+        const DATE_WINDOW_DAYS: i64 = 3;
+        let mut claimed = vec![false; self.data.transactions.len()];
+        for row in &mut report.rows {
+            let tx = &row.transaction;
+            // A matching bank transaction id is a certain duplicate
+            let same_id = tx.external_id.as_ref().and_then(|id| {
+                self.data.transactions.iter().position(|existing| {
+                    existing.external_id.as_ref() == Some(id) && existing.account == tx.account
+                })
+            });
+            if let Some(idx) = same_id {
+                claimed[idx] = true;
+                row.set_duplicate(idx, &self.data.transactions[idx]);
+                row.action = DuplicateAction::Skip;
+                continue;
+            }
+            let best = self.data.transactions.iter().enumerate()
+                .filter(|(i, existing)| {
+                    !claimed[*i]
+                        && existing.currency == tx.currency
+                        && (existing.amount - tx.amount).abs() < 0.005
+                        && (existing.date - tx.date).num_days().abs() <= DATE_WINDOW_DAYS
+                        && similar_descriptions(&existing.description, &tx.description)
+                })
+                .min_by_key(|(_, existing)| (existing.date - tx.date).num_days().abs())
+                .map(|(i, _)| i);
+            if let Some(idx) = best {
+                claimed[idx] = true;
+                row.set_duplicate(idx, &self.data.transactions[idx]);
+                row.action = DuplicateAction::Skip;
+            }
+        }
+    }
+}
+
+impl ImportRow {
+    fn set_duplicate(&mut self, idx: usize, existing: &Transaction) {
+        self.duplicate_of = Some(idx);
+        self.duplicate_key = Some((existing.date, existing.amount, existing.description.clone()));
+    }
+
+    /// Where the suspected duplicate is now in `transactions`, found again by its date, amount
+    /// and description if transactions were deleted since it was flagged. `None` if it is gone.
+    pub fn duplicate_in(&self, transactions: &[Transaction]) -> Option<usize> {
+        let (date, amount, description) = self.duplicate_key.as_ref()?;
+        let matches = |tx: &Transaction| tx.date == *date && tx.amount == *amount && tx.description == *description;
+        match self.duplicate_of {
+            Some(idx) if transactions.get(idx).is_some_and(matches) => Some(idx),
+            _ => transactions.iter().position(matches),
+        }
+    }
+}
+
+/// Parses an import file. `currency` is used where the format doesn't record one (QIF, and
+/// ledger amounts without a commodity).
+pub fn parse_import(format: ImportFormat, path: &PathBuf, currency: Currency) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    match format {
+        ImportFormat::Csv => parse_csv(path),
+        ImportFormat::Ofx => {
+            // QFX files are frequently Windows-1252 rather than UTF-8
+            let content = fs::read(path)?;
+            Ok(crate::ofx::parse_ofx(&String::from_utf8_lossy(&content)))
+        }
+        ImportFormat::Qif => {
+            let content = fs::read(path)?;
+            Ok(parse_qif(&String::from_utf8_lossy(&content), currency))
+        }
+        ImportFormat::Camt053 => Ok(crate::camt::parse_camt053(&fs::read_to_string(path)?)?),
+        ImportFormat::Mt940 => {
+            let content = fs::read(path)?;
+            Ok(crate::mt940::parse_mt940(&String::from_utf8_lossy(&content)))
+        }
+        ImportFormat::Ledger => Ok(crate::ledger::parse_ledger(&fs::read_to_string(path)?, currency)),
+    }
+}
+
+pub fn parse_csv(path: &PathBuf) -> Result<ImportReport, Box<dyn std::error::Error>> {
+    let mut rdr = ReaderBuilder::new().from_path(path)?;
+    // Tags are optional and found by name, so older exports without the column still import
+    let tags_column = rdr.headers()?.iter().position(|h| h.trim().eq_ignore_ascii_case("tags"));
+    let mut report = ImportReport::default();
+    for result in rdr.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line() as usize).unwrap_or(0);
+                report.issues.push(ImportIssue { line, field: "row".into(), reason: e.to_string() });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line() as usize).unwrap_or(0);
+        let mut issues = Vec::new();
+        let mut field = |idx: usize, name: &str| match record.get(idx) {
+            Some(value) => Some(value.trim().to_string()),
+            None => {
+                issues.push(ImportIssue { line, field: name.into(), reason: "missing column".into() });
+                None
+            }
+        };
+        let date = field(0, "date");
+        let description = field(1, "description").unwrap_or_default();
+        let amount = field(2, "amount");
+        let currency = field(3, "currency");
+        let category = field(4, "category").unwrap_or_default();
+        let recurring = field(5, "recurring");
+        let tags = tags_column.and_then(|i| record.get(i)).map(parse_tags).unwrap_or_default();
+
+        let date = date.and_then(|d| match NaiveDate::parse_from_str(&d, "%Y-%m-%d") {
+            Ok(date) => Some(date),
+            Err(_) => {
+                issues.push(ImportIssue { line, field: "date".into(), reason: format!("'{d}' is not a YYYY-MM-DD date") });
+                None
+            }
+        });
+        let amount = amount.and_then(|a| match a.parse::<f64>() {
+            Ok(amount) if amount.is_finite() => Some(amount),
+            _ => {
+                issues.push(ImportIssue { line, field: "amount".into(), reason: format!("'{a}' is not a number") });
+                None
+            }
+        });
+        let currency = currency.and_then(|c| {
+            if c.is_empty() {
+                Some(Currency::USD)
+            } else if let Ok(currency) = Currency::from_str(&c) {
+                Some(currency)
+            } else {
+                issues.push(ImportIssue { line, field: "currency".into(), reason: format!("unsupported currency '{c}'") });
+                None
+            }
+        });
+        let recurring = recurring.and_then(|r| match r.parse::<bool>() {
+            Ok(recurring) => Some(recurring),
+            Err(_) => {
+                issues.push(ImportIssue { line, field: "recurring".into(), reason: format!("'{r}' is not true/false") });
+                None
+            }
+        });
+
+        match (date, amount, currency, recurring) {
+            (Some(date), Some(amount), Some(currency), Some(recurring)) if issues.is_empty() => {
+                report.rows.push(ImportRow::new(Transaction {
+                    date, description, amount, category, recurring, currency, tags, ..Default::default()
+                }));
+            }
+            _ => report.issues.extend(issues),
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Default)]
+struct QifRecord {
+    line: usize,
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    splits: Vec<(String, Option<String>, String)>,
+}
+
+/// Parses the transaction sections of a QIF file. Category, class and memorized lists are
+/// skipped; split lines become the lines of a split transaction.
+pub fn parse_qif(content: &str, currency: Currency) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut account = String::new();
+    let mut in_account_header = false;
+    let mut in_transactions = false;
+    let mut record = QifRecord::default();
+
+    let content = content.strip_prefix('\u{FEFF}').unwrap_or(content);
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim().to_lowercase();
+            in_account_header = header == "account";
+            in_transactions = header.starts_with("type:")
+                && !["type:cat", "type:class", "type:memorized", "type:invst", "type:prices"]
+                    .iter()
+                    .any(|skip| header.starts_with(skip));
+            record = QifRecord::default();
+            continue;
+        }
+        // The code is one character, which isn't ASCII in garbled or mis-encoded files
+        let (code, value) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        let value = value.trim().to_string();
+        if in_account_header {
+            match code {
+                "N" => account = value,
+                "^" => in_account_header = false,
+                _ => {}
+            }
+            continue;
+        }
+        if !in_transactions {
+            continue;
+        }
+        if record.line == 0 {
+            record.line = i + 1;
+        }
+        match code {
+            "D" => record.date = Some(value),
+            "T" | "U" => record.amount = Some(value),
+            "P" => record.payee = Some(value),
+            "M" => record.memo = Some(value),
+            "L" => record.category = Some(value),
+            "S" => record.splits.push((value, None, String::new())),
+            "E" => {
+                if let Some(split) = record.splits.last_mut() {
+                    split.1 = Some(value);
+                }
+            }
+            "$" => {
+                if let Some(split) = record.splits.last_mut() {
+                    split.2 = value;
+                }
+            }
+            "^" => finish_qif_record(std::mem::take(&mut record), currency, &account, &mut report),
+            _ => {}
+        }
+    }
+    report
+}
+
+fn finish_qif_record(record: QifRecord, currency: Currency, account: &str, report: &mut ImportReport) {
+    let line = record.line;
+    let mut issues = Vec::new();
+    let date = match record.date.as_deref() {
+        None => {
+            issues.push(ImportIssue { line, field: "D".into(), reason: "missing date".into() });
+            None
+        }
+        Some(d) => {
+            let parsed = parse_qif_date(d);
+            if parsed.is_none() {
+                issues.push(ImportIssue { line, field: "D".into(), reason: format!("unrecognised date '{d}'") });
+            }
+            parsed
+        }
+    };
+    let amount = match record.amount.as_deref() {
+        None => {
+            issues.push(ImportIssue { line, field: "T".into(), reason: "missing amount".into() });
+            None
+        }
+        Some(a) => {
+            let parsed = parse_qif_amount(a);
+            if parsed.is_none() {
+                issues.push(ImportIssue { line, field: "T".into(), reason: format!("'{a}' is not a number") });
+            }
+            parsed
+        }
+    };
+    let mut splits = Vec::new();
+    for (category, memo, split_amount) in &record.splits {
+        match parse_qif_amount(split_amount) {
+            Some(a) => splits.push((qif_category(category), memo.clone(), a)),
+            None => issues.push(ImportIssue { line, field: "$".into(), reason: format!("split amount '{split_amount}' is not a number") }),
+        }
+    }
+    if let Some(total) = amount {
+        let split_total: f64 = splits.iter().map(|s| s.2).sum();
+        if !splits.is_empty() && (split_total - total).abs() > 0.005 {
+            issues.push(ImportIssue {
+                line,
+                field: "$".into(),
+                reason: format!("splits add up to {split_total:.2}, not {total:.2}"),
+            });
+        }
+    }
+    let (Some(date), Some(amount)) = (date, amount) else {
+        report.issues.extend(issues);
+        return;
+    };
+    if !issues.is_empty() {
+        report.issues.extend(issues);
+        return;
+    }
+
+    let description = match (record.payee, record.memo) {
+        (Some(payee), Some(memo)) if !memo.is_empty() && memo != payee => format!("{payee} - {memo}"),
+        (Some(payee), _) => payee,
+        (None, Some(memo)) => memo,
+        (None, None) => String::new(),
+    };
+    let base = Transaction {
+        date,
+        description,
+        amount: -amount,
+        category: record.category.as_deref().map(qif_category).unwrap_or_default(),
+        currency,
+        account: account.to_string(),
+        ..Default::default()
+    };
+    let mut tx = base;
+    tx.splits = splits
+        .into_iter()
+        .map(|(category, memo, split_amount)| Split { category, amount: -split_amount, memo: memo.unwrap_or_default() })
+        .collect();
+    if tx.category.is_empty() {
+        if let Some(first) = tx.splits.first() {
+            tx.category = first.category.clone();
+        }
+    }
+    report.rows.push(ImportRow::new(tx));
+}
+
+/// QIF categories may carry a class (`Food/Business`); transfers are written as `[Account]`.
+fn qif_category(category: &str) -> String {
+    let category = category.split('/').next().unwrap_or("").trim();
+    if category.starts_with('[') && category.ends_with(']') {
+        "Transfer".to_string()
+    } else {
+        // QIF writes subcategories as `Parent:Child`
+        normalize_category(&category.replace(':', ">"))
+    }
+}
+
+/// Accepts the date styles seen in the wild: `MM/DD/YYYY`, `M/D'YY`, `MM/DD/YY`, `DD.MM.YYYY`
+/// and ISO `YYYY-MM-DD`. Day-first slashed dates are used when the month-first reading fails.
+fn parse_qif_date(date: &str) -> Option<NaiveDate> {
+    let normalized: String = date.trim().replace('\'', "/").replace(' ', "");
+    let four_digit_year = normalized.rsplit(['/', '.', '-']).next().is_some_and(|y| y.len() == 4);
+    let formats: &[&str] = if normalized.contains('-') && normalized.len() == 10 && normalized.as_bytes()[4] == b'-' {
+        &["%Y-%m-%d"]
+    } else if normalized.contains('.') {
+        if four_digit_year { &["%d.%m.%Y"] } else { &["%d.%m.%y"] }
+    } else if four_digit_year {
+        &["%m/%d/%Y", "%d/%m/%Y", "%m-%d-%Y"]
+    } else {
+        &["%m/%d/%y", "%d/%m/%y", "%m-%d-%y"]
+    };
+    formats.iter().find_map(|f| NaiveDate::parse_from_str(&normalized, f).ok())
+}
+
+/// Parses `1,234.56`, `-12.50` and the European `1.234,56`.
+fn parse_qif_amount(amount: &str) -> Option<f64> {
+    let amount = amount.trim().replace(' ', "");
+    let normalized = match (amount.rfind(','), amount.rfind('.')) {
+        (Some(comma), Some(dot)) if comma > dot => amount.replace('.', "").replace(',', "."),
+        (Some(comma), None) if amount.len() - comma == 3 => amount.replace(',', "."),
+        _ => amount.replace(',', ""),
+    };
+    normalized.parse::<f64>().ok().filter(|a| a.is_finite())
+}
+
+fn description_tokens(description: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+/// Loose description match: one contains the other, or at least half the words are shared.
+fn similar_descriptions(a: &str, b: &str) -> bool {
+    let (a_norm, b_norm) = (a.trim().to_lowercase(), b.trim().to_lowercase());
+    if a_norm.is_empty() || b_norm.is_empty() || a_norm.contains(&b_norm) || b_norm.contains(&a_norm) {
+        return true;
+    }
+    let (a_tokens, b_tokens) = (description_tokens(a), description_tokens(b));
+    let shared = a_tokens.iter().filter(|t| b_tokens.contains(t)).count();
+    let total = a_tokens.len() + b_tokens.len() - shared;
+    total > 0 && shared * 2 >= total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn qif_date_variants() {
+        let cases = [
+            ("09/15/2026", date(2026, 9, 15)),
+            ("9/5/26", date(2026, 9, 5)),
+            ("1/2'05", date(2005, 1, 2)),
+            ("12/31' 9", date(2009, 12, 31)),
+            ("25/12/2026", date(2026, 12, 25)),
+            ("15.09.2026", date(2026, 9, 15)),
+            ("15.09.26", date(2026, 9, 15)),
+            ("2026-09-15", date(2026, 9, 15)),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_qif_date(text), Some(expected), "{text}");
+        }
+        assert_eq!(parse_qif_date("31/31/2026"), None);
+        assert_eq!(parse_qif_date("yesterday"), None);
+    }
+
+    #[test]
+    fn qif_amounts() {
+        assert_eq!(parse_qif_amount("-1,234.56"), Some(-1234.56));
+        assert_eq!(parse_qif_amount("1.234,56"), Some(1234.56));
+        assert_eq!(parse_qif_amount("-12,50"), Some(-12.5));
+        assert_eq!(parse_qif_amount("2 000.00"), Some(2000.0));
+        assert_eq!(parse_qif_amount("NaN"), None);
+        assert_eq!(parse_qif_amount("abc"), None);
+    }
+
+    #[test]
+    fn qif_signs_are_flipped_to_spending_positive() {
+        let report = parse_qif("!Type:Bank\nD09/01/2026\nT-45.00\nPGrocer\nLFood:Groceries\n^\nD09/02/2026\nU2,500.00\nPSalary\n^\n", Currency::EUR);
+        assert!(report.issues.is_empty());
+        let [grocer, salary] = &report.rows[..] else { panic!("expected two rows") };
+        assert_eq!(grocer.transaction.amount, 45.0);
+        assert_eq!(grocer.transaction.category, "Food > Groceries");
+        assert_eq!(grocer.transaction.currency, Currency::EUR);
+        assert_eq!(salary.transaction.amount, -2500.0);
+    }
+
+    #[test]
+    fn qif_splits_must_add_up() {
+        let content = "!Type:CCard\nD10/03/2026\nT-100.00\nPSupermarket\nSFood\nEweekly shop\n$-70.00\nSHousehold:Cleaning\n$-30.00\n^\n\
+                       D10/04/2026\nT-50.00\nPShop\nSFood\n$-20.00\nSFun\n$-20.00\n^\n";
+        let report = parse_qif(content, Currency::USD);
+        assert_eq!(report.rows.len(), 1);
+        let tx = &report.rows[0].transaction;
+        assert_eq!(tx.category, "Food");
+        let lines: Vec<(&str, f64, &str)> = tx.splits.iter().map(|s| (s.category.as_str(), s.amount, s.memo.as_str())).collect();
+        assert_eq!(lines, [("Food", 70.0, "weekly shop"), ("Household > Cleaning", 30.0, "")]);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].field, "$");
+        assert_eq!(report.issues[0].line, 11);
+    }
+
+    #[test]
+    fn qif_skips_lists_and_reads_the_account_header() {
+        let content = "!Type:Cat\nNFood\n^\n!Account\nNVisa\nTCCard\n^\n!Type:CCard\nD10/05/2026\nT-9.99\nPStreaming\nL[Checking]\n^\n";
+        let report = parse_qif(content, Currency::USD);
+        assert_eq!(report.rows.len(), 1);
+        let tx = &report.rows[0].transaction;
+        assert_eq!((tx.account.as_str(), tx.category.as_str(), tx.amount), ("Visa", "Transfer", 9.99));
+    }
+
+    #[test]
+    fn qif_with_byte_order_mark() {
+        let report = parse_qif("\u{FEFF}!Type:Bank\r\nD09/01/2026\r\nT-5.00\r\nPCafé\r\n\u{FFFD}garbled\r\n^\r\n", Currency::USD);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.rows.len(), 1);
+        assert_eq!(report.rows[0].transaction.description, "Café");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::categories::is_within;
+use crate::types::*;
+use chrono::Local;
+use std::collections::BTreeMap;
+
+/// Balances may be off by rounding.
+const ENVELOPE_TOLERANCE: f64 = 0.005;
+
+/// Where the money stands in envelope mode, in the base currency.
+#[derive(Default)]
+pub struct EnvelopeSummary {
+    /// Income that didn't go back into an envelope as a refund.
+    pub income: f64,
+    pub assigned: f64,
+    /// Spending in categories without an envelope.
+    pub unbudgeted: f64,
+    /// Assigned and spent per envelope.
+    pub envelopes: BTreeMap<String, (f64, f64)>,
+}
+
+impl EnvelopeSummary {
+    /// Income still waiting to be put into an envelope; negative when more was assigned or
+    /// spent outside envelopes than came in.
+    pub fn available(&self) -> f64 {
+        self.income - self.assigned - self.unbudgeted
+    }
+
+    pub fn balance(&self, envelope: &str) -> f64 {
+        self.envelopes.get(envelope).map_or(0.0, |(assigned, spent)| assigned - spent)
+    }
+}
+
+impl AppState {
+    /// Totals up income, assignments and spending per envelope. Spending and refunds count
+    /// towards the envelope of the closest category they are within; other incoming money is
+    /// income.
+    pub fn envelope_summary(&self) -> EnvelopeSummary {
+        let base = self.base_currency;
+        let mut summary = EnvelopeSummary::default();
+        for a in &self.data.budget.assignments {
+            let amount = self.convert(a.amount, a.currency, base);
+            summary.envelopes.entry(a.category.clone()).or_default().0 += amount;
+            summary.assigned += amount;
+        }
+        let names: Vec<String> = summary.envelopes.keys().cloned().collect();
+        for tx in &self.data.transactions {
+            for (category, amount) in tx.category_amounts() {
+                let amount = self.convert(amount, tx.currency, base);
+                let envelope = names.iter().filter(|e| is_within(category, e)).max_by_key(|e| e.len());
+                match envelope {
+                    Some(envelope) => summary.envelopes.get_mut(envelope).unwrap().1 += amount,
+                    None if amount < 0.0 => summary.income -= amount,
+                    None => summary.unbudgeted += amount,
+                }
+            }
+        }
+        summary
+    }
+
+    fn record_assignment(&mut self, category: &str, amount: f64, memo: String) {
+        self.data.budget.assignments.push(Assignment {
+            date: Local::now().date_naive(),
+            category: category.to_string(),
+            amount,
+            currency: self.base_currency,
+            memo,
+        });
+    }
+
+    /// Puts `amount` (in the base currency) of the money available to assign into `category`'s
+    /// envelope. A negative amount takes money out of the envelope again.
+    pub fn assign_to_envelope(&mut self, category: &str, amount: f64) -> Result<(), String> {
+        if category.trim().is_empty() {
+            return Err("choose a category".to_string());
+        }
+        if !amount.is_finite() {
+            return Err("the amount must be a number".to_string());
+        }
+        let summary = self.envelope_summary();
+        if amount > summary.available() + ENVELOPE_TOLERANCE {
+            return Err(format!("only {:.2} {} is available to assign", summary.available().max(0.0), self.base_currency));
+        }
+        if -amount > summary.balance(category) + ENVELOPE_TOLERANCE {
+            return Err(format!("{category} only holds {:.2} {}", summary.balance(category), self.base_currency));
+        }
+        self.record_assignment(category, amount, String::new());
+        Ok(())
+    }
+
+    /// Moves `amount` (in the base currency) from one envelope to another.
+    pub fn move_between_envelopes(&mut self, from: &str, to: &str, amount: f64) -> Result<(), String> {
+        if from == to || to.trim().is_empty() {
+            return Err("choose two different envelopes".to_string());
+        }
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err("the amount to move must be positive".to_string());
+        }
+        let balance = self.envelope_summary().balance(from);
+        if amount > balance + ENVELOPE_TOLERANCE {
+            return Err(format!("{from} only holds {balance:.2} {}", self.base_currency));
+        }
+        self.record_assignment(from, -amount, format!("Moved to {to}"));
+        self.record_assignment(to, amount, format!("Moved from {from}"));
+        Ok(())
+    }
+}
@@ -0,0 +1,257 @@
+use crate::categories::{category_ancestors, is_within};
+use crate::types::*;
+use chrono::{Days, Months, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+
+/// Transactions without an account are forecast in this one.
+pub const DEFAULT_ACCOUNT: &str = "Checking";
+
+fn account_of(tx: &Transaction) -> &str {
+    if tx.account.is_empty() { DEFAULT_ACCOUNT } else { &tx.account }
+}
+
+/// Where a forecast item comes from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ForecastSource {
+    Recurring,
+    Scheduled,
+    Loan,
+    Budget,
+}
+
+impl ForecastSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ForecastSource::Recurring => "Recurring",
+            ForecastSource::Scheduled => "Scheduled",
+            ForecastSource::Loan => "Loan",
+            ForecastSource::Budget => "Budget",
+        }
+    }
+}
+
+/// Money expected to come in or go out, in the base currency. Positive amounts go out, as
+/// with transactions.
+pub struct ForecastItem {
+    pub date: NaiveDate,
+    pub description: String,
+    pub account: String,
+    pub category: String,
+    pub amount: f64,
+    pub source: ForecastSource,
+}
+
+pub struct Forecast {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Everything expected, by date.
+    pub items: Vec<ForecastItem>,
+    /// Each account's balance today and after every day with items.
+    pub balances: BTreeMap<String, Vec<(NaiveDate, f64)>>,
+}
+
+impl Forecast {
+    /// The first day each account's balance would go below zero, and the balance then.
+    pub fn warnings(&self) -> Vec<(&str, NaiveDate, f64)> {
+        self.balances
+            .iter()
+            .filter_map(|(account, points)| points.iter().find(|(_, b)| *b < 0.0).map(|&(d, b)| (account.as_str(), d, b)))
+            .collect()
+    }
+}
+
+/// How far apart the occurrences of a recurring transaction are.
+#[derive(Clone, Copy)]
+enum Interval {
+    Days(u64),
+    Months(u32),
+}
+
+impl Interval {
+    /// Rounds a typical gap to whole months where it is close to one.
+    fn from_gap(days: i64) -> Self {
+        match days {
+            26..=35 => Interval::Months(1),
+            56..=65 => Interval::Months(2),
+            85..=95 => Interval::Months(3),
+            170..=190 => Interval::Months(6),
+            350..=380 => Interval::Months(12),
+            days => Interval::Days(days.max(1) as u64),
+        }
+    }
+
+    /// Occurrence `n` after `date`.
+    fn after(&self, date: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match *self {
+            Interval::Days(days) => date.checked_add_days(Days::new(days * n as u64)),
+            Interval::Months(months) => date.checked_add_months(Months::new(months * n)),
+        }
+    }
+
+    fn days(&self) -> i64 {
+        match *self {
+            Interval::Days(days) => days as i64,
+            Interval::Months(months) => months as i64 * 30,
+        }
+    }
+}
+
+impl AppState {
+    /// Every account used by a transaction or given a balance, sorted.
+    pub fn accounts(&self) -> Vec<String> {
+        let mut accounts: Vec<String> = self
+            .data
+            .transactions
+            .iter()
+            .map(|t| account_of(t).to_string())
+            .chain(self.data.account_balances.keys().cloned())
+            .collect();
+        accounts.sort();
+        accounts.dedup();
+        accounts
+    }
+
+    /// Future occurrences of recurring transactions. Transactions with the same description
+    /// and account form a series, repeated at its usual interval after its latest occurrence.
+    /// Series that have missed two occurrences are assumed to have ended.
+    fn forecast_recurring(&self, today: NaiveDate, end: NaiveDate) -> Vec<ForecastItem> {
+        let mut series: HashMap<(String, &str), Vec<&Transaction>> = HashMap::new();
+        for tx in self.data.transactions.iter().filter(|t| t.recurring) {
+            series.entry((tx.description.trim().to_lowercase(), account_of(tx))).or_default().push(tx);
+        }
+        let mut items = Vec::new();
+        for occurrences in series.values_mut() {
+            occurrences.sort_by_key(|t| t.date);
+            let mut gaps: Vec<i64> = occurrences.windows(2).map(|w| (w[1].date - w[0].date).num_days()).filter(|&d| d > 0).collect();
+            gaps.sort();
+            let interval = gaps.get(gaps.len() / 2).map_or(Interval::Months(1), |&gap| Interval::from_gap(gap));
+            let last = occurrences[occurrences.len() - 1];
+            if (today - last.date).num_days() > 2 * interval.days() {
+                continue;
+            }
+            let amount = self.convert(last.amount, last.currency, self.base_currency);
+            for date in (1..).map_while(|n| interval.after(last.date, n)).take_while(|&d| d <= end) {
+                if date > today {
+                    items.push(ForecastItem {
+                        date,
+                        description: last.description.clone(),
+                        account: account_of(last).to_string(),
+                        category: last.category.clone(),
+                        amount,
+                        source: ForecastSource::Recurring,
+                    });
+                }
+            }
+        }
+        items
+    }
+
+    /// Remaining installments of loans whose payments aren't already recurring transactions.
+    fn forecast_loans(&self, today: NaiveDate, end: NaiveDate) -> Vec<ForecastItem> {
+        let mut items = Vec::new();
+        for loan in &self.data.loans {
+            let in_loan = |t: &&Transaction| t.any_category(|c| is_within(c, &loan.category));
+            if self.data.transactions.iter().filter(in_loan).any(|t| t.recurring) {
+                continue;
+            }
+            let last_payment = self.data.transactions.iter().filter(in_loan).map(|t| t.date).max();
+            let after = last_payment.map_or(today, |d| d.max(today));
+            for row in loan.schedule().into_iter().filter(|r| r.date > after && r.date <= end) {
+                items.push(ForecastItem {
+                    date: row.date,
+                    description: format!("{} installment {}", loan.name, row.number),
+                    account: DEFAULT_ACCOUNT.to_string(),
+                    category: loan.category.clone(),
+                    amount: self.convert(row.payment, loan.currency, self.base_currency),
+                    source: ForecastSource::Loan,
+                });
+            }
+        }
+        items
+    }
+
+    /// What is left of each budget in every period up to `end`, less what other items already
+    /// expect to spend in it. It is taken out at the start of each period (tomorrow for the
+    /// current one), from the account the category is usually paid from. Budgets within a
+    /// budgeted parent are left to the parent.
+    fn forecast_budgets(&self, today: NaiveDate, end: NaiveDate, expected: &[ForecastItem]) -> Vec<ForecastItem> {
+        let limits = &self.data.budget.limits;
+        let mut items = Vec::new();
+        for (category, limit) in limits {
+            if category_ancestors(category).iter().any(|a| a != category && limits.contains_key(a)) {
+                continue;
+            }
+            let mut accounts: HashMap<&str, usize> = HashMap::new();
+            for tx in self.data.transactions.iter().filter(|t| t.any_category(|c| is_within(c, category))) {
+                *accounts.entry(account_of(tx)).or_default() += 1;
+            }
+            let account = accounts.into_iter().max_by_key(|&(a, n)| (n, std::cmp::Reverse(a))).map_or(DEFAULT_ACCOUNT, |(a, _)| a);
+            let current = self.budget_history(category, today).pop();
+            let (mut start, mut next) = limit.period.containing(limit.start_day, today);
+            while start <= end {
+                let (from, left) = match &current {
+                    Some(progress) if progress.start == start => (today + Days::new(1), progress.remaining()),
+                    _ => {
+                        let version = self.data.budget.limit_on(category, start).unwrap_or(limit);
+                        (start, self.convert(version.amount, version.currency, self.base_currency))
+                    }
+                };
+                let already: f64 = expected
+                    .iter()
+                    .filter(|i| i.date >= start && i.date < next && is_within(&i.category, category))
+                    .map(|i| i.amount)
+                    .sum();
+                let amount = left - already;
+                if amount > 0.005 && from <= end {
+                    items.push(ForecastItem {
+                        date: from,
+                        description: format!("{category} budget"),
+                        account: account.to_string(),
+                        category: category.clone(),
+                        amount,
+                        source: ForecastSource::Budget,
+                    });
+                }
+                (start, next) = limit.period.containing(limit.start_day, next);
+            }
+        }
+        items
+    }
+
+    /// Projects every account's balance over the next `months` months from today's balances,
+    /// recurring transactions, future-dated transactions, loan installments and what is left
+    /// of the budgets.
+    pub fn forecast(&self, today: NaiveDate, months: u32) -> Forecast {
+        let end = today.checked_add_months(Months::new(months)).unwrap_or(today);
+        let mut items = self.forecast_recurring(today, end);
+        for tx in self.data.transactions.iter().filter(|t| t.date > today && t.date <= end) {
+            items.push(ForecastItem {
+                date: tx.date,
+                description: tx.description.clone(),
+                account: account_of(tx).to_string(),
+                category: tx.category.clone(),
+                amount: self.convert(tx.amount, tx.currency, self.base_currency),
+                source: ForecastSource::Scheduled,
+            });
+        }
+        items.extend(self.forecast_loans(today, end));
+        let budgets = self.forecast_budgets(today, end, &items);
+        items.extend(budgets);
+        items.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.description.cmp(&b.description)));
+
+        let mut balances: BTreeMap<String, Vec<(NaiveDate, f64)>> = BTreeMap::new();
+        for account in self.accounts() {
+            let balance = self.data.account_balances.get(&account).copied().unwrap_or(0.0);
+            balances.insert(account, vec![(today, balance)]);
+        }
+        for item in &items {
+            let points = balances.entry(item.account.clone()).or_insert_with(|| vec![(today, 0.0)]);
+            let balance = points.last().map_or(0.0, |p| p.1) - item.amount;
+            match points.last_mut() {
+                Some(last) if last.0 == item.date => last.1 = balance,
+                _ => points.push((item.date, balance)),
+            }
+        }
+        Forecast { start: today, end, items, balances }
+    }
+}
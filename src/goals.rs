@@ -0,0 +1,101 @@
+use crate::categories::is_within;
+use crate::tags::has_tag;
+use crate::types::*;
+use chrono::{Days, Local, NaiveDate};
+
+/// Average days per month, for contributions per month.
+const DAYS_PER_MONTH: f64 = 30.44;
+/// Contributions over this many days set the pace of the projection.
+const RECENT_DAYS: u64 = 90;
+
+/// How far a goal has come, in the goal's currency.
+pub struct GoalProgress {
+    pub saved: f64,
+    /// Saved per month over the last three months.
+    pub monthly_rate: f64,
+    /// Needed per month to reach the target by the target date.
+    pub monthly_needed: Option<f64>,
+    /// When the target is reached at the current pace.
+    pub projected: Option<NaiveDate>,
+}
+
+impl GoalProgress {
+    pub fn fraction(&self, goal: &SavingsGoal) -> f64 {
+        if goal.target > 0.0 { (self.saved / goal.target).clamp(0.0, 1.0) } else { 1.0 }
+    }
+
+    pub fn is_reached(&self, goal: &SavingsGoal) -> bool {
+        self.saved >= goal.target
+    }
+}
+
+impl SavingsGoal {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("a goal needs a name".to_string());
+        }
+        if !self.target.is_finite() || self.target <= 0.0 {
+            return Err("the target must be positive".to_string());
+        }
+        if !self.initial.is_finite() {
+            return Err("the amount already saved must be a number".to_string());
+        }
+        if self.category.is_empty() && self.account.is_empty() && self.tag.is_empty() {
+            return Err("link the goal to a category, account or tag".to_string());
+        }
+        Ok(())
+    }
+
+    /// How much `tx` puts towards the goal, in the transaction's currency, if it is linked.
+    fn contribution(&self, tx: &Transaction) -> Option<f64> {
+        if !self.account.is_empty() && !tx.account.eq_ignore_ascii_case(&self.account) {
+            return None;
+        }
+        if !self.tag.is_empty() && !has_tag(tx, &self.tag) {
+            return None;
+        }
+        let amount = if self.category.is_empty() {
+            tx.amount
+        } else {
+            let lines = tx.category_amounts();
+            let mut linked = lines.iter().filter(|(c, _)| is_within(c, &self.category)).peekable();
+            linked.peek()?;
+            linked.map(|(_, amount)| amount).sum()
+        };
+        // Money coming into a savings account is negative in its statement
+        Some(if self.account.is_empty() { amount } else { -amount })
+    }
+}
+
+impl AppState {
+    pub fn goal_progress(&self, goal: &SavingsGoal) -> GoalProgress {
+        let today = Local::now().date_naive();
+        let recent_start = today - Days::new(RECENT_DAYS);
+        let (mut saved, mut recent) = (goal.initial, 0.0);
+        for tx in &self.data.transactions {
+            if let Some(amount) = goal.contribution(tx) {
+                let amount = self.convert(amount, tx.currency, goal.currency);
+                saved += amount;
+                if tx.date > recent_start && tx.date <= today {
+                    recent += amount;
+                }
+            }
+        }
+        let remaining = (goal.target - saved).max(0.0);
+        let monthly_rate = recent / (RECENT_DAYS as f64 / DAYS_PER_MONTH);
+        let monthly_needed = goal.target_date.map(|date| {
+            let months = (date - today).num_days() as f64 / DAYS_PER_MONTH;
+            // Whatever is left is due at once when less than a month remains
+            remaining / months.max(1.0)
+        });
+        let projected = if remaining <= 0.0 {
+            Some(today)
+        } else if monthly_rate > 0.0 {
+            let days = (remaining / monthly_rate * DAYS_PER_MONTH).ceil() as u64;
+            today.checked_add_days(Days::new(days))
+        } else {
+            None
+        };
+        GoalProgress { saved, monthly_rate, monthly_needed, projected }
+    }
+}
@@ -1,7 +1,9 @@
+use crate::categories::{category_depth, category_leaf, is_within, normalize_category};
+use crate::tags::parse_tags;
 use crate::types::*;
 use eframe::egui;
 use egui_extras::{Column, TableBuilder};
-use chrono::Local;
+use chrono::{Datelike, Local};
 
 pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     let state = &mut app.state;
@@ -12,6 +14,7 @@ pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _f
             if ui.button("New").clicked() {
                 state.data = crate::types::BudgetAppData::default();
                 state.file_path = None;
+                state.clear_selection();
             }
             if ui.button("Save As...").clicked() {
                 if let Some(path) = rfd::FileDialog::new().save_file() {
@@ -24,31 +27,145 @@ pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _f
                     state.load(path);
                 }
             }
-            if ui.button("Import CSV").clicked() {
-                if let Some(path) = rfd::FileDialog::new().pick_file() {
-                    // TODO: Show error in GUI
-                    match state.import_csv(&path) {
-                        Ok(_) => {
-                            state.rates_api_error = Some("CSV imported successfully.".to_string());
-                        },
-                        Err(e) => {
-                            state.rates_api_error = Some(format!("CSV import failed: {e}"));
+            ui.menu_button("Import", |ui| {
+                for &format in ImportFormat::all() {
+                    if ui.button(format.label()).clicked() {
+                        ui.close_menu();
+                        let picked = rfd::FileDialog::new()
+                            .add_filter(format.label(), format.extensions())
+                            .pick_file();
+                        if let Some(path) = picked {
+                            match state.import_file(format, &path, false) {
+                                Ok((report, added)) if !report.needs_review() => {
+                                    state.save();
+                                    state.rates_api_error = Some(format!(
+                                        "{} imported successfully ({added} transactions).",
+                                        format.label()
+                                    ));
+                                },
+                                Ok((report, _)) => {
+                                    // Nothing is imported until the user reviews flagged rows and duplicates
+                                    state.pending_import = Some(report);
+                                    state.import_path = Some(path);
+                                    state.show_import_modal = true;
+                                },
+                                Err(e) => {
+                                    state.rates_api_error = Some(format!("{} import failed: {e}", format.label()));
+                                }
+                            }
                         }
                     }
                 }
-            }
-            if ui.button("Export CSV").clicked() {
-                if let Some(path) = rfd::FileDialog::new().save_file() {
-                    // TODO: Show error in GUI
-                    match state.export_csv(&path) {
-                        Ok(_) => {
-                            state.rates_api_error = Some("CSV exported successfully.".to_string());
+            });
+            ui.menu_button("Export", |ui| {
+                ui.checkbox(&mut state.export_options.filtered, "Only filtered transactions");
+                ui.checkbox(&mut state.export_options.include_converted, "Include amount in base currency");
+                ui.separator();
+                if ui.button("CSV").clicked() {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).save_file() {
+                        match state.export_csv(&path, &state.export_options) {
+                            Ok(_) => {
+                                state.rates_api_error = Some("CSV exported successfully.".to_string());
+                            },
+                            Err(e) => {
+                                state.rates_api_error = Some(format!("CSV export failed: {e}"));
+                            }
+                        }
+                    }
+                }
+                if ui.button("QIF").clicked() {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new().add_filter("QIF", &["qif"]).save_file() {
+                        match state.export_qif(&path, &state.export_options) {
+                            Ok(_) => {
+                                state.rates_api_error = Some(format!(
+                                    "QIF exported successfully (amounts in {}).",
+                                    state.base_currency
+                                ));
+                            },
+                            Err(e) => {
+                                state.rates_api_error = Some(format!("QIF export failed: {e}"));
+                            }
+                        }
+                    }
+                }
+                if ui.button("Ledger journal").clicked() {
+                    ui.close_menu();
+                    let picked = rfd::FileDialog::new()
+                        .add_filter("Ledger journal", &["journal", "ledger"])
+                        .save_file();
+                    if let Some(path) = picked {
+                        match state.export_ledger(&path, &state.export_options) {
+                            Ok(_) => {
+                                state.rates_api_error = Some("Ledger journal exported successfully.".to_string());
+                            },
+                            Err(e) => {
+                                state.rates_api_error = Some(format!("Ledger export failed: {e}"));
+                            }
+                        }
+                    }
+                }
+                if ui.button("Spreadsheet report (XLSX)").clicked() {
+                    ui.close_menu();
+                    if let Some(path) = rfd::FileDialog::new().add_filter("Excel workbook", &["xlsx"]).save_file() {
+                        match state.export_xlsx(&path, &state.export_options) {
+                            Ok(_) => {
+                                state.rates_api_error = Some("Spreadsheet report exported successfully.".to_string());
+                            },
+                            Err(e) => {
+                                state.rates_api_error = Some(format!("Spreadsheet export failed: {e}"));
+                            }
+                        }
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Month (YYYY-MM):");
+                    ui.add(egui::TextEdit::singleline(&mut state.report_month).desired_width(70.0));
+                });
+                if ui.button("Monthly statement (PDF)").clicked() {
+                    ui.close_menu();
+                    match crate::reports::parse_month(&state.report_month) {
+                        Some((year, month)) => {
+                            let picked = rfd::FileDialog::new()
+                                .add_filter("PDF", &["pdf"])
+                                .set_file_name(format!("statement-{}.pdf", state.report_month.trim()))
+                                .save_file();
+                            if let Some(path) = picked {
+                                match state.export_monthly_pdf(year, month, &path) {
+                                    Ok(_) => {
+                                        state.rates_api_error = Some("Monthly statement exported successfully.".to_string());
+                                    },
+                                    Err(e) => {
+                                        state.rates_api_error = Some(format!("PDF export failed: {e}"));
+                                    }
+                                }
+                            }
                         },
-                        Err(e) => {
-                            state.rates_api_error = Some(format!("CSV export failed: {e}"));
+                        None => {
+                            state.rates_api_error = Some("Failed to parse month. Use YYYY-MM.".to_string());
                         }
                     }
                 }
+            });
+            if ui.button("Categories").clicked() {
+                state.show_category_manager = true;
+            }
+            if ui.button("Rules").clicked() {
+                state.show_rules = true;
+            }
+            if ui.button("Budget History").clicked() {
+                state.show_budget_history = true;
+            }
+            if ui.button("Goals").clicked() {
+                state.show_goals = true;
+            }
+            if ui.button("Loans").clicked() {
+                state.show_loans = true;
+            }
+            if ui.button("Forecast").clicked() {
+                state.show_forecast = true;
             }
             if ui.button("Edit Exchange Rates").clicked() {
                 state.editing_rates = true;
@@ -91,6 +208,7 @@ pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _f
                                 ui.label(format!("{} -> {}", from.as_str(), to.as_str()));
                                 if ui.add(egui::DragValue::new(&mut val).speed(0.001)).changed() {
                                     state.exchange_rates.insert(key, val);
+                                    state.invalidate_views();
                                 }
                             });
                         }
@@ -110,119 +228,607 @@ pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _f
     state.editing_rates = editing_rates;
     if state.rates_api_error.as_deref() == Some("fetch") {
         state.fetch_exchange_rates();
+        state.invalidate_views();
         state.rates_api_error = Some("Exchange rates updated from API.".to_string());
     }
 
-    egui::SidePanel::left("side").show(ctx, |ui| {
-        ui.heading("Add Transaction");
-        ui.label("Date (YYYY-MM-DD):");
-        ui.text_edit_singleline(&mut state.input_date_str);
-        ui.label("Description:");
-        ui.text_edit_singleline(&mut state.input_desc);
-        ui.label("Amount:");
-        ui.text_edit_singleline(&mut state.input_amt);
-        ui.label("Category:");
-        ui.text_edit_singleline(&mut state.input_cat);
-        egui::ComboBox::from_id_source("input_currency")
-            .selected_text(state.input_currency.as_str())
-            .show_ui(ui, |ui| {
-                for c in Currency::all() {
-                    ui.selectable_value(&mut state.input_currency, *c, c.as_str());
-                }
-            });
-        ui.checkbox(&mut state.input_recurring, "Recurring");
-        if ui.button("Add").clicked() {
-            if let Ok(amount) = state.input_amt.parse::<f64>() {
-                if let Ok(date) = chrono::NaiveDate::parse_from_str(&state.input_date_str, "%Y-%m-%d") {
-                    let transaction = Transaction {
-                        date,
-                        description: state.input_desc.clone(),
-                        amount,
-                        category: state.input_cat.clone(),
-                        recurring: state.input_recurring,
-                        currency: state.input_currency,
-                    };
-                    state.data.transactions.push(transaction);
-                    state.input_desc.clear();
-                    state.input_amt.clear();
-                    state.input_cat.clear();
-                    state.input_date_str = Local::now().date_naive().to_string();
-                    state.input_recurring = false;
-                    state.save();
-                    // Show a status message for success
-                    state.rates_api_error = Some("Transaction added.".to_string());
-                } else {
-                    // Show a status message for date parse failure
-                    state.rates_api_error = Some("Failed to parse date. Use YYYY-MM-DD.".to_string());
-                }
-            } else {
-                // Show a status message for amount parse failure
-                state.rates_api_error = Some("Failed to parse amount (must be a number).".to_string());
-            }
+    if state.show_category_manager {
+        draw_category_manager(ctx, state);
+    }
+    if state.show_rules {
+        draw_rules(ctx, state);
+    }
+    if state.show_budget_history {
+        draw_budget_history(ctx, state);
+    }
+    if state.show_goals {
+        draw_goals(ctx, state);
+    }
+    if state.show_loans {
+        draw_loans(ctx, state);
+    }
+    if state.show_forecast {
+        draw_forecast(ctx, state);
+    }
+
+    // Import review modal: shown when an import has flagged rows or suspected duplicates
+    let mut show_import_modal = state.show_import_modal;
+    let mut import_choice = None;
+    if show_import_modal {
+        if let Some(report) = &mut state.pending_import {
+            let title = state.import_path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .and_then(|n| n.to_str())
+                .unwrap_or("file");
+            let existing = &state.data.transactions;
+            egui::Window::new("Import Review")
+                .open(&mut show_import_modal)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "{}: {} valid rows, {} flagged rows, {} suspected duplicates. Nothing has been imported yet.",
+                        title,
+                        report.rows.len(),
+                        report.flagged_rows(),
+                        report.duplicates()
+                    ));
+                    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                        for issue in &report.issues {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!("Line {}: {} — {}", issue.line, issue.field, issue.reason),
+                            );
+                        }
+                        if report.duplicates() > 0 {
+                            ui.separator();
+                            ui.label("Suspected duplicates:");
+                            egui::Grid::new("import_duplicates").striped(true).show(ui, |ui| {
+                                ui.label("Imported");
+                                ui.label("Existing");
+                                ui.label("Action");
+                                ui.end_row();
+                                for (i, row) in report.rows.iter_mut().enumerate() {
+                                    let Some(dup) = row.duplicate_in(existing).map(|d| &existing[d]) else { continue };
+                                    let tx = &row.transaction;
+                                    ui.label(format!("{} {} {:.2} {}", tx.date, tx.description, tx.amount, tx.currency));
+                                    ui.label(format!("{} {} {:.2} {}", dup.date, dup.description, dup.amount, dup.currency));
+                                    ui.horizontal(|ui| {
+                                        ui.push_id(i, |ui| {
+                                            ui.radio_value(&mut row.action, DuplicateAction::Keep, "Keep");
+                                            ui.radio_value(&mut row.action, DuplicateAction::Skip, "Skip");
+                                            ui.radio_value(&mut row.action, DuplicateAction::Merge, "Merge");
+                                        });
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Import").clicked() {
+                            import_choice = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            import_choice = Some(false);
+                        }
+                    });
+                });
         }
-        if let Some(idx) = state.selected_tx {
-            if ui.button("Delete Selected").clicked() {
-                state.data.transactions.remove(idx);
-                state.selected_tx = None;
+    }
+    if let Some(import) = import_choice {
+        if let Some(report) = state.pending_import.take() {
+            if import {
+                let added = state.apply_import(&report);
                 state.save();
-                // Show a status message for delete
-                state.rates_api_error = Some("Transaction deleted.".to_string());
+                state.rates_api_error = Some(format!(
+                    "Imported {added} transactions, skipped {} flagged rows and {} duplicates.",
+                    report.flagged_rows(),
+                    report.rows.iter().filter(|r| r.action == DuplicateAction::Skip).count()
+                ));
+            } else {
+                state.rates_api_error = Some("Import cancelled.".to_string());
             }
         }
-        ui.separator();
-        ui.heading("Budgets");
-
-        // FIX: Avoid borrow checker error by operating on copies and writing back if changed.
-        let categories = state.categories();
-        for cat in categories {
-            // Get current values
-            let (mut amount, mut currency) = {
-                let entry = state.data.budget.monthly_limits
-                    .get(&cat)
-                    .cloned()
-                    .unwrap_or(CategoryBudget { amount: 0.0, currency: state.base_currency });
-                (entry.amount, entry.currency)
-            };
+        show_import_modal = false;
+    }
+    if !show_import_modal {
+        state.pending_import = None;
+        state.import_path = None;
+    }
+    state.show_import_modal = show_import_modal;
 
-            let mut changed = false;
+    egui::SidePanel::left("side").show(ctx, |ui| {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Add Transaction");
+            ui.label("Date (YYYY-MM-DD):");
+            ui.text_edit_singleline(&mut state.input_date_str);
+            if state.suggestions_for != state.input_desc {
+                state.suggestions_for = state.input_desc.clone();
+                state.category_suggestions = state.suggest_categories(&state.input_desc);
+                state.description_completions = state.description_completions(&state.input_desc, 6);
+            }
+            ui.label("Description:");
+            let completions = state.description_completions.clone();
+            autocomplete(ui, "input_desc_completions", &mut state.input_desc, &completions);
+            ui.label("Amount:");
+            ui.text_edit_singleline(&mut state.input_amt);
+            ui.label("Category:");
+            let typed = state.input_cat.trim().to_lowercase();
+            let category_completions: Vec<String> = if typed.is_empty() {
+                Vec::new()
+            } else {
+                state.categories().into_iter().filter(|c| c.to_lowercase().contains(&typed) && *c != state.input_cat).take(6).collect()
+            };
+            autocomplete(ui, "input_cat_completions", &mut state.input_cat, &category_completions);
+            if state.input_cat.trim().is_empty() && !state.category_suggestions.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Suggested:");
+                    for (category, probability) in state.category_suggestions.clone() {
+                        if ui.small_button(format!("{category} ({:.0}%)", probability * 100.0)).clicked() {
+                            state.input_cat = category;
+                        }
+                    }
+                });
+            }
+            egui::ComboBox::from_id_source("input_currency")
+                .selected_text(state.input_currency.as_str())
+                .show_ui(ui, |ui| {
+                    for c in Currency::all() {
+                        ui.selectable_value(&mut state.input_currency, *c, c.as_str());
+                    }
+                });
+            ui.label("Tags:");
+            ui.add(egui::TextEdit::singleline(&mut state.input_tags).hint_text("vacation2026, reimbursable"));
+            ui.checkbox(&mut state.input_recurring, "Recurring");
+            if ui.button("Add").clicked() {
+                if let Ok(amount) = state.input_amt.parse::<f64>() {
+                    if let Ok(date) = chrono::NaiveDate::parse_from_str(&state.input_date_str, "%Y-%m-%d") {
+                        let typed_category = normalize_category(&state.input_cat);
+                        let mut transaction = Transaction {
+                            date,
+                            description: state.input_desc.clone(),
+                            amount,
+                            category: typed_category.clone(),
+                            recurring: state.input_recurring,
+                            currency: state.input_currency,
+                            tags: parse_tags(&state.input_tags),
+                            ..Default::default()
+                        };
+                        // Rules add their tags as on import; a typed category wins over the rule's
+                        state.apply_rules(&mut transaction);
+                        if !typed_category.is_empty() {
+                            transaction.category = typed_category;
+                        }
+                        state.data.transactions.push(transaction);
+                        state.input_desc.clear();
+                        state.input_amt.clear();
+                        state.input_cat.clear();
+                        state.input_tags.clear();
+                        state.input_date_str = Local::now().date_naive().to_string();
+                        state.input_recurring = false;
+                        state.save();
+                        // Show a status message for success
+                        state.rates_api_error = Some("Transaction added.".to_string());
+                    } else {
+                        // Show a status message for date parse failure
+                        state.rates_api_error = Some("Failed to parse date. Use YYYY-MM-DD.".to_string());
+                    }
+                } else {
+                    // Show a status message for amount parse failure
+                    state.rates_api_error = Some("Failed to parse amount (must be a number).".to_string());
+                }
+            }
+            if !state.selection.is_empty() {
+                ui.separator();
+                ui.heading(format!("{} Selected", state.selection.len()));
+                ui.horizontal(|ui| {
+                    if ui.button("Delete").clicked() {
+                        let count = state.delete_selected();
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) deleted."));
+                    }
+                    if ui.button("Clear selection").clicked() {
+                        state.clear_selection();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.bulk_category).hint_text("Category").desired_width(100.0));
+                    if ui.button("Set category").clicked() {
+                        let category = normalize_category(&state.bulk_category);
+                        let count = state.set_category_selected(&category);
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) moved to '{category}'."));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("bulk_currency")
+                        .selected_text(state.bulk_currency.as_str())
+                        .show_ui(ui, |ui| {
+                            for &c in Currency::all() {
+                                ui.selectable_value(&mut state.bulk_currency, c, c.as_str());
+                            }
+                        });
+                    if ui.button("Set currency").clicked() {
+                        let count = state.set_currency_selected(state.bulk_currency);
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) set to {}.", state.bulk_currency));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Mark recurring").clicked() {
+                        let count = state.set_recurring_selected(true);
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) marked recurring."));
+                    }
+                    if ui.button("Mark one-off").clicked() {
+                        let count = state.set_recurring_selected(false);
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) marked one-off."));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.bulk_tag).hint_text("Tag").desired_width(100.0));
+                    if ui.button("Add tag").clicked() && !state.bulk_tag.trim().is_empty() {
+                        let tag = state.bulk_tag.clone();
+                        let count = state.tag_selected(&tag);
+                        state.save();
+                        state.rates_api_error = Some(format!("{count} transaction(s) tagged '{}'.", tag.trim()));
+                    }
+                });
+                if ui.button("Export selection (CSV)...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).save_file() {
+                        let options = ExportOptions { selection: true, ..state.export_options };
+                        match state.export_csv(&path, &options) {
+                            Ok(_) => state.rates_api_error = Some("Selection exported successfully.".to_string()),
+                            Err(e) => state.rates_api_error = Some(format!("CSV export failed: {e}")),
+                        }
+                    }
+                }
+            }
+            if let Some(i) = state.selected_tx.filter(|&i| i < state.data.transactions.len()) {
+                draw_selected_tags(ui, state, i);
+            }
+            if let Some(i) = state.selected_tx.filter(|&i| i < state.data.transactions.len()) {
+                ui.separator();
+                ui.heading("Split Selected");
+                let total = state.data.transactions[i].amount;
+                if state.split_tx != Some(i) {
+                    let tx = &state.data.transactions[i];
+                    for split in &tx.splits {
+                        ui.label(format!("{}: {:.2}", split.category, split.amount));
+                    }
+                    if ui.button(if tx.is_split() { "Edit split" } else { "Split across categories..." }).clicked() {
+                        state.split_lines = if tx.is_split() {
+                            tx.splits.iter().map(|s| (s.category.clone(), format!("{:.2}", s.amount), s.memo.clone())).collect()
+                        } else {
+                            vec![(tx.category.clone(), format!("{:.2}", tx.amount), String::new()), Default::default()]
+                        };
+                        state.split_tx = Some(i);
+                    }
+                } else {
+                    let mut remove = None;
+                    for (n, (category, amount, memo)) in state.split_lines.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(category).hint_text("Category").desired_width(100.0));
+                            ui.add(egui::TextEdit::singleline(amount).hint_text("Amount").desired_width(60.0));
+                            ui.add(egui::TextEdit::singleline(memo).hint_text("Memo").desired_width(70.0));
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(n);
+                            }
+                        });
+                    }
+                    if let Some(n) = remove {
+                        state.split_lines.remove(n);
+                    }
+                    let assigned: f64 = state.split_lines.iter().filter_map(|l| l.1.trim().parse::<f64>().ok()).sum();
+                    let unassigned = total - assigned;
+                    ui.horizontal(|ui| {
+                        if ui.button("Add line").clicked() {
+                            let rest = if unassigned.abs() > 0.005 { format!("{unassigned:.2}") } else { String::new() };
+                            state.split_lines.push((String::new(), rest, String::new()));
+                        }
+                        let color = if unassigned.abs() > 0.005 { egui::Color32::RED } else { ui.visuals().text_color() };
+                        ui.colored_label(color, format!("Unassigned: {unassigned:.2}"));
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Save split").clicked() {
+                            let lines: Result<Vec<Split>, String> = state
+                                .split_lines
+                                .iter()
+                                .filter(|l| !(l.0.trim().is_empty() && l.1.trim().is_empty()))
+                                .map(|(category, amount, memo)| {
+                                    let amount = amount.trim().parse::<f64>().map_err(|_| format!("'{amount}' is not an amount"))?;
+                                    Ok(Split { category: normalize_category(category), amount, memo: memo.trim().to_string() })
+                                })
+                                .collect();
+                            match lines.and_then(|lines| state.set_splits(i, lines)) {
+                                Ok(()) => {
+                                    state.split_tx = None;
+                                    state.save();
+                                    state.rates_api_error = Some("Split saved.".to_string());
+                                }
+                                Err(e) => state.rates_api_error = Some(format!("Split not saved: {e}")),
+                            }
+                        }
+                        if state.data.transactions[i].is_split() && ui.button("Unsplit").clicked() {
+                            let _ = state.set_splits(i, Vec::new());
+                            state.split_tx = None;
+                            state.save();
+                            state.rates_api_error = Some("Split removed.".to_string());
+                        }
+                        if ui.button("Cancel").clicked() {
+                            state.split_tx = None;
+                        }
+                    });
+                }
+            }
+            draw_tag_totals(ui, state);
+            ui.separator();
             ui.horizontal(|ui| {
-                ui.label(&cat);
-                changed |= ui.add(egui::DragValue::new(&mut amount)).changed();
-                egui::ComboBox::from_id_source(format!("budget_curr_{}", cat))
-                    .selected_text(currency.as_str())
-                    .show_ui(ui, |ui| {
-                        for &c in Currency::all() {
-                            if ui.selectable_value(&mut currency, c, c.as_str()).changed() {
-                                changed = true;
+                ui.heading("Envelopes");
+                let toggle = ui.checkbox(&mut state.data.budget.envelope_mode, "On");
+                if toggle.on_hover_text("Zero-based budgeting: assign income into category envelopes").changed() {
+                    state.save();
+                }
+            });
+            if state.data.budget.envelope_mode {
+                let summary = state.envelope_summary();
+                let available = summary.available();
+                let color = if available < -0.005 {
+                    egui::Color32::RED
+                } else if available > 0.005 {
+                    egui::Color32::from_rgb(60, 160, 60)
+                } else {
+                    ui.visuals().text_color()
+                };
+                ui.colored_label(color, format!("Available to assign: {available:.2} {}", state.base_currency))
+                    .on_hover_text(format!(
+                        "Income {:.2}, assigned {:.2}, spent outside envelopes {:.2}",
+                        summary.income, summary.assigned, summary.unbudgeted
+                    ));
+                egui::Grid::new("envelopes").striped(true).show(ui, |ui| {
+                    for heading in ["Envelope", "Assigned", "Spent", "Balance"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for (envelope, (assigned, spent)) in &summary.envelopes {
+                        let selected = state.envelope_category == *envelope;
+                        if ui.selectable_label(selected, category_leaf(envelope)).on_hover_text(envelope).clicked() {
+                            state.envelope_category = envelope.clone();
+                        }
+                        ui.label(format!("{assigned:.2}"));
+                        ui.label(format!("{spent:.2}"));
+                        let balance = assigned - spent;
+                        let color = if balance < -0.005 { egui::Color32::RED } else { ui.visuals().text_color() };
+                        ui.colored_label(color, format!("{balance:.2}"));
+                        ui.end_row();
+                    }
+                });
+                let categories = state.categories();
+                ui.horizontal(|ui| {
+                    ui.label("Envelope:");
+                    autocomplete(ui, "envelope_category", &mut state.envelope_category, &categories);
+                });
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.envelope_amount).hint_text("Amount").desired_width(70.0));
+                    let category = normalize_category(&state.envelope_category);
+                    let amount = state.envelope_amount.trim().parse::<f64>();
+                    let mut result = None;
+                    if ui.button("Assign").on_hover_text("A negative amount takes money out").clicked() {
+                        result = Some(match amount {
+                            Ok(amount) => state.assign_to_envelope(&category, amount).map(|_| format!("Assigned {amount:.2} to {category}.")),
+                            Err(_) => Err("enter an amount".to_string()),
+                        });
+                    }
+                    if ui.add_enabled(available > 0.005, egui::Button::new("Assign rest")).clicked() {
+                        result = Some(state.assign_to_envelope(&category, available).map(|_| format!("Assigned {available:.2} to {category}.")));
+                    }
+                    egui::ComboBox::from_id_source("envelope_move_to")
+                        .selected_text(state.envelope_move_to.clone().unwrap_or_else(|| "Move to...".to_string()))
+                        .show_ui(ui, |ui| {
+                            for target in &categories {
+                                ui.selectable_value(&mut state.envelope_move_to, Some(target.clone()), target);
                             }
+                        });
+                    if ui.add_enabled(state.envelope_move_to.is_some(), egui::Button::new("Move")).clicked() {
+                        let to = state.envelope_move_to.clone().unwrap_or_default();
+                        result = Some(match amount {
+                            Ok(amount) => state
+                                .move_between_envelopes(&category, &to, amount)
+                                .map(|_| format!("Moved {amount:.2} from {category} to {to}.")),
+                            Err(_) => Err("enter an amount".to_string()),
+                        });
+                    }
+                    match result {
+                        Some(Ok(message)) => {
+                            state.envelope_amount.clear();
+                            state.save();
+                            state.rates_api_error = Some(message);
                         }
+                        Some(Err(e)) => state.rates_api_error = Some(format!("Envelope not changed: {e}")),
+                        None => {}
+                    }
+                });
+            }
+            ui.separator();
+            ui.heading("Budgets");
+
+            // FIX: Avoid borrow checker error by operating on copies and writing back if changed.
+            let categories = state.categories();
+            let today = chrono::Local::now().date_naive();
+            for cat in categories {
+                // Get current values
+                let (mut amount, mut currency, mut period, mut start_day, mut rollover) = {
+                    let entry = state.data.budget.limits.get(&cat).cloned().unwrap_or(CategoryBudget {
+                        amount: 0.0,
+                        currency: state.base_currency,
+                        period: BudgetPeriod::Monthly,
+                        start_day: 1,
+                        rollover: Rollover::default(),
+                        effective: None,
                     });
-                if ui.button("Set").clicked() || changed {
-                    state.data.budget.monthly_limits.insert(cat.clone(), CategoryBudget { amount, currency });
+                    (entry.amount, entry.currency, entry.period, entry.start_day, entry.rollover)
+                };
+
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    ui.add_space(category_depth(&cat) as f32 * 12.0);
+                    ui.label(category_leaf(&cat)).on_hover_text(&cat);
+                    changed |= ui.add(egui::DragValue::new(&mut amount)).changed();
+                    egui::ComboBox::from_id_source(format!("budget_curr_{}", cat))
+                        .selected_text(currency.as_str())
+                        .show_ui(ui, |ui| {
+                            for &c in Currency::all() {
+                                if ui.selectable_value(&mut currency, c, c.as_str()).changed() {
+                                    changed = true;
+                                }
+                            }
+                        });
+                    egui::ComboBox::from_id_source(format!("budget_period_{}", cat))
+                        .selected_text(period.as_str())
+                        .show_ui(ui, |ui| {
+                            for &p in BudgetPeriod::all() {
+                                changed |= ui.selectable_value(&mut period, p, p.as_str()).changed();
+                            }
+                        });
+                    start_day = start_day.clamp(1, period.max_start_day());
+                    egui::ComboBox::from_id_source(format!("budget_start_{}", cat))
+                        .selected_text(period.start_day_label(start_day))
+                        .show_ui(ui, |ui| {
+                            for day in 1..=period.max_start_day() {
+                                changed |= ui.selectable_value(&mut start_day, day, period.start_day_label(day)).changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text("First day of each budget period");
+                    changed |= ui.button("Set").clicked();
+                    let converted = state.convert(amount, currency, state.base_currency);
+                    if currency != state.base_currency {
+                        ui.label(format!("≈ {:.2} {}", converted, state.base_currency));
+                    }
+                });
+                let history = state.cached_budget_history(&cat, today);
+                if let Some(progress) = history.last().filter(|p| p.budget > 0.0) {
+                    let last = progress.end.pred_opt().unwrap_or(progress.end);
+                    let allowance = progress.allowance();
+                    let used = if allowance > 0.0 { progress.spent / allowance } else { 1.0 };
+                    let mut text = format!("{:.2} of {:.2} {}", progress.spent, allowance, state.base_currency);
+                    if progress.carried.abs() >= 0.005 {
+                        text.push_str(&format!(" ({:+.2} carried)", progress.carried));
+                    }
+                    let bar = egui::ProgressBar::new(used.clamp(0.0, 1.0) as f32).text(text).desired_width(220.0);
+                    let bar = if progress.remaining() < 0.0 { bar.fill(egui::Color32::from_rgb(200, 70, 70)) } else { bar };
+                    let indent = category_depth(&cat) as f32 * 12.0 + 12.0;
+                    ui.horizontal(|ui| {
+                        ui.add_space(indent);
+                        ui.add(bar).on_hover_text(format!("{} to {}", progress.start, last));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(indent);
+                        egui::CollapsingHeader::new("Rollover").id_source(format!("rollover_{cat}")).show(ui, |ui| {
+                            changed |= ui.checkbox(&mut rollover.unspent, "Carry unspent budget forward").changed();
+                            changed |= ui.checkbox(&mut rollover.overspent, "Take overspending off the next period").changed();
+                            ui.horizontal(|ui| {
+                                let mut capped = rollover.cap.is_some();
+                                changed |= ui.checkbox(&mut capped, "Cap carried budget at").changed();
+                                let mut cap = rollover.cap.unwrap_or(amount);
+                                changed |= ui.add_enabled(capped, egui::DragValue::new(&mut cap)).changed();
+                                rollover.cap = capped.then_some(cap);
+                                ui.label(currency.as_str());
+                            });
+                            if rollover.is_enabled() {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!("Carrying since {}", history[0].start));
+                                    if ui.button("Start from this period").clicked() {
+                                        rollover.since = Some(progress.start);
+                                        changed = true;
+                                    }
+                                });
+                                egui::Grid::new(format!("rollover_history_{cat}")).striped(true).show(ui, |ui| {
+                                    for heading in ["Period", "Carried in", "Spent", "Left"] {
+                                        ui.strong(heading);
+                                    }
+                                    ui.end_row();
+                                    // The most recent periods, newest first
+                                    for period in history.iter().rev().take(12) {
+                                        ui.label(period.start.to_string());
+                                        ui.label(format!("{:+.2}", period.carried));
+                                        ui.label(format!("{:.2}", period.spent));
+                                        let left = period.remaining();
+                                        let color = if left < 0.0 { egui::Color32::RED } else { ui.visuals().text_color() };
+                                        ui.colored_label(color, format!("{left:.2}"));
+                                        ui.end_row();
+                                    }
+                                });
+                            }
+                        });
+                    });
+                }
+                if changed {
+                    // Earlier periods keep the limit they had
+                    let limit = CategoryBudget { amount, currency, period, start_day, rollover, effective: None };
+                    state.data.budget.set_limit(&cat, limit, today);
                     state.save();
                     // Show a status message for budget update
                     state.rates_api_error = Some(format!("Budget updated for category '{}'.", cat));
                 }
-                let converted = state.convert(amount, currency, state.base_currency);
-                if currency != state.base_currency {
-                    ui.label(format!("≈ {:.2} {}", converted, state.base_currency));
-                }
-            });
-        }
-        // Show status/error message (used for TODOs above)
-        if let Some(msg) = &state.rates_api_error {
-            ui.separator();
-            ui.colored_label(egui::Color32::LIGHT_BLUE, msg);
-        }
+            }
+            // Show status/error message (used for TODOs above)
+            if let Some(msg) = &state.rates_api_error {
+                ui.separator();
+                ui.colored_label(egui::Color32::LIGHT_BLUE, msg);
+            }
+        });
     });
 
     egui::TopBottomPanel::bottom("stats").show(ctx, |ui| {
         ui.horizontal(|ui| {
             ui.label("Search:");
-            ui.text_edit_singleline(&mut state.search_term);
+            let query_errors = crate::query::Query::parse(&state.search_term).errors;
+            let search_color = if query_errors.is_empty() { ui.visuals().text_color() } else { egui::Color32::RED };
+            ui.add(
+                egui::TextEdit::singleline(&mut state.search_term)
+                    .hint_text("cat:food amount>50 date:2026-09")
+                    .text_color(search_color),
+            )
+            .on_hover_text(if query_errors.is_empty() { crate::query::HELP.to_string() } else { query_errors.join("\n") });
+            let (from, to) = state.filter_date_range();
+            ui.label("From:");
+            let from_color = if from.is_none() && !state.filter_from.trim().is_empty() { egui::Color32::RED } else { ui.visuals().text_color() };
+            ui.add(egui::TextEdit::singleline(&mut state.filter_from).desired_width(80.0).hint_text("YYYY-MM-DD").text_color(from_color));
+            ui.label("To:");
+            let to_color = if to.is_none() && !state.filter_to.trim().is_empty() { egui::Color32::RED } else { ui.visuals().text_color() };
+            ui.add(egui::TextEdit::singleline(&mut state.filter_to).desired_width(80.0).hint_text("YYYY-MM-DD").text_color(to_color));
+            let categories = state.categories();
+            egui::ComboBox::from_id_source("filter_category")
+                .selected_text(state.filter_category.clone().unwrap_or_else(|| "All categories".to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.filter_category, None, "All categories");
+                    for cat in categories {
+                        let label = format!("{}{}", "    ".repeat(category_depth(&cat)), category_leaf(&cat));
+                        ui.selectable_value(&mut state.filter_category, Some(cat.clone()), label);
+                    }
+                });
+            egui::ComboBox::from_id_source("filter_currency")
+                .selected_text(state.filter_currency.map_or("All currencies", |c| c.as_str()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.filter_currency, None, "All currencies");
+                    for c in Currency::all() {
+                        ui.selectable_value(&mut state.filter_currency, Some(*c), c.as_str());
+                    }
+                });
+            draw_tag_filter(ui, state);
+            if ui.button("Clear").clicked() {
+                state.search_term.clear();
+                state.filter_from.clear();
+                state.filter_to.clear();
+                state.filter_category = None;
+                state.filter_currency = None;
+                state.filter_tag = None;
+            }
             ui.label(format!(
                 "Total: {:.2} {}",
-                state.total(),
+                state.table_total(),
                 state.base_currency
             ));
         });
@@ -235,53 +841,996 @@ pub fn draw_main_window(app: &mut crate::app::BudgetApp, ctx: &egui::Context, _f
         ));
 
         // Borrow checker fix: collect filtered indices first
-        let filtered_indices: Vec<usize> = state.data.transactions.iter().enumerate()
-            .filter(|(_, tx)| {
-                state.search_term.is_empty() ||
-                    tx.description.to_lowercase().contains(&state.search_term.to_lowercase()) ||
-                    tx.category.to_lowercase().contains(&state.search_term.to_lowercase())
-            })
-            .map(|(i, _)| i)
-            .collect();
+        let rows = state.table_rows();
+        let base = state.base_currency;
+        let modifiers = ui.input(|i| i.modifiers);
+        let mut clicked_row = None;
+        let mut tag_action: Option<(usize, String, bool)> = None; // (row, tag, add)
 
         TableBuilder::new(ui)
             .striped(true)
+            .resizable(true)
+            .sense(egui::Sense::click())
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-            .columns(Column::auto(), 7)
+            .column(Column::initial(90.0).at_least(70.0))
+            .column(Column::initial(220.0).at_least(60.0).clip(true))
+            .column(Column::initial(80.0).at_least(50.0))
+            .column(Column::initial(60.0).at_least(40.0))
+            .column(Column::initial(100.0).at_least(50.0))
+            .column(Column::initial(120.0).at_least(50.0).clip(true))
+            .column(Column::initial(70.0).at_least(40.0))
+            .column(Column::initial(100.0).at_least(40.0).clip(true))
+            .column(Column::initial(120.0).at_least(40.0).clip(true))
+            .column(Column::remainder().at_least(30.0))
             .header(20.0, |mut header| {
-                header.col(|ui| { ui.label("Date"); });
-                header.col(|ui| { ui.label("Description"); });
-                header.col(|ui| { ui.label("Amount"); });
-                header.col(|ui| { ui.label("Currency"); });
-                header.col(|ui| { ui.label("Category"); });
-                header.col(|ui| { ui.label("Recurring"); });
-                header.col(|ui| { ui.label("Select"); });
+                header.col(|ui| sort_header(ui, state, "Date", SortColumn::Date));
+                header.col(|ui| sort_header(ui, state, "Description", SortColumn::Description));
+                header.col(|ui| sort_header(ui, state, "Amount", SortColumn::Amount));
+                header.col(|ui| { ui.strong("Currency"); });
+                header.col(|ui| sort_header(ui, state, &format!("Amount ({base})"), SortColumn::Converted));
+                header.col(|ui| sort_header(ui, state, "Category", SortColumn::Category));
+                header.col(|ui| { ui.strong("Recurring"); });
+                header.col(|ui| { ui.strong("Account"); });
+                header.col(|ui| { ui.strong("Tags").on_hover_text("Right-click a row to edit its tags"); });
+                header.col(|ui| {
+                    let mut all = !rows.is_empty() && rows.iter().all(|i| state.selection.contains(i));
+                    if ui.checkbox(&mut all, "").on_hover_text("Select all filtered").changed() {
+                        if all {
+                            state.selection.extend(rows.iter());
+                        } else {
+                            state.clear_selection();
+                        }
+                    }
+                });
             })
-            .body(|mut body| {
-                for &i in &filtered_indices {
+            .body(|body| {
+                // Only the visible rows are laid out
+                body.rows(18.0, rows.len(), |mut row| {
+                    let i = rows[row.index()];
                     let tx = &state.data.transactions[i];
-                    body.row(18.0, |mut row| {
-                        row.col(|ui| { ui.label(tx.date.to_string()); });
-                        row.col(|ui| { ui.label(&tx.description); });
-                        row.col(|ui| {
-                            ui.label(format!("{:.2}", tx.amount));
+                    let converted = state.convert(tx.amount, tx.currency, base);
+                    row.set_selected(state.selection.contains(&i));
+                    row.col(|ui| { ui.label(tx.date.to_string()); });
+                    row.col(|ui| { ui.label(&tx.description); });
+                    row.col(|ui| {
+                        ui.label(format!("{:.2}", tx.amount));
+                    });
+                    row.col(|ui| { ui.label(tx.currency.as_str()); });
+                    row.col(|ui| { ui.label(format!("{:.2}", converted)); });
+                    row.col(|ui| {
+                        let label = ui.label(category_text(state, &tx.category, &tx.category_label()));
+                        if tx.is_split() {
+                            let lines: Vec<String> = tx.splits.iter().map(|s| format!("{}: {:.2}", s.category, s.amount)).collect();
+                            label.on_hover_text(lines.join("\n"));
+                        }
+                    });
+                    row.col(|ui| { if tx.recurring { ui.label("Yes"); } else { ui.label("No"); } });
+                    row.col(|ui| { ui.label(&tx.account); });
+                    row.col(|ui| {
+                        for tag in &tx.tags {
+                            if ui.small_button(format!("#{tag}")).on_hover_text("Show only this tag").clicked() {
+                                state.filter_tag = Some(tag.clone());
+                            }
+                        }
+                    });
+                    row.col(|ui| {
+                        let mut checked = state.selection.contains(&i);
+                        if ui.checkbox(&mut checked, "").changed() {
+                            clicked_row = Some((i, true));
+                        }
+                    });
+                    let response = row.response();
+                    if response.clicked() {
+                        clicked_row = Some((i, modifiers.command));
+                    }
+                    response.context_menu(|ui| {
+                        ui.strong("Tags");
+                        for tag in &tx.tags {
+                            if ui.button(format!("Remove #{tag}")).clicked() {
+                                tag_action = Some((i, tag.clone(), false));
+                                ui.close_menu();
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            let edit = ui.add(egui::TextEdit::singleline(&mut state.tag_input).hint_text("new tag").desired_width(100.0));
+                            let entered = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            if (ui.button("Add").clicked() || entered) && !state.tag_input.trim().is_empty() {
+                                tag_action = Some((i, state.tag_input.clone(), true));
+                                ui.close_menu();
+                            }
                         });
-                        row.col(|ui| { ui.label(tx.currency.as_str()); });
-                        row.col(|ui| { ui.label(&tx.category); });
-                        row.col(|ui| { if tx.recurring { ui.label("Yes"); } else { ui.label("No"); } });
-                        row.col(|ui| {
-                            if ui.button("Select").clicked() {
-                                state.selected_tx = Some(i);
-                                state.input_desc = tx.description.clone();
-                                state.input_amt = format!("{}", tx.amount);
-                                state.input_cat = tx.category.clone();
-                                state.input_date_str = tx.date.to_string();
-                                state.input_recurring = tx.recurring;
-                                state.input_currency = tx.currency;
+                    });
+                });
+            });
+
+        if let Some((i, tag, add)) = tag_action {
+            if add { state.add_tag(i, &tag) } else { state.remove_tag(i, &tag) };
+            state.tag_input.clear();
+            state.save();
+        }
+
+        if let Some((i, toggle)) = clicked_row {
+            state.click_row(i, &rows, toggle, modifiers.shift);
+            if !toggle && !modifiers.shift {
+                let tx = &state.data.transactions[i];
+                state.input_desc = tx.description.clone();
+                state.input_amt = format!("{}", tx.amount);
+                state.input_cat = tx.category.clone();
+                state.input_date_str = tx.date.to_string();
+                state.input_recurring = tx.recurring;
+                state.input_currency = tx.currency;
+                state.input_tags = tx.tags.join(", ");
+            }
+        }
+    });
+}
+
+/// Tags of the selected transaction `i`, each with a button to remove it, and a field to add one.
+fn draw_selected_tags(ui: &mut egui::Ui, state: &mut AppState, i: usize) {
+    ui.separator();
+    ui.heading("Tags of Selected");
+    let tags = state.data.transactions[i].tags.clone();
+    let mut changed = false;
+    ui.horizontal_wrapped(|ui| {
+        if tags.is_empty() {
+            ui.label("No tags");
+        }
+        for tag in &tags {
+            if ui.small_button(format!("#{tag} ✖")).on_hover_text("Remove tag").clicked() {
+                changed |= state.remove_tag(i, tag);
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut state.tag_input).hint_text("new tag").desired_width(100.0));
+        if ui.button("Add tag").clicked() {
+            let tag = state.tag_input.clone();
+            changed |= state.add_tag(i, &tag);
+            state.tag_input.clear();
+        }
+    });
+    if changed {
+        state.save();
+    }
+}
+
+/// Dropdown limiting the table to one tag, shown once any transaction is tagged.
+fn draw_tag_filter(ui: &mut egui::Ui, state: &mut AppState) {
+    let tags = state.tags();
+    if !tags.is_empty() || state.filter_tag.is_some() {
+        egui::ComboBox::from_id_source("filter_tag")
+            .selected_text(state.filter_tag.as_ref().map_or("All tags".to_string(), |t| format!("#{t}")))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.filter_tag, None, "All tags");
+                for tag in tags {
+                    let label = format!("#{tag}");
+                    ui.selectable_value(&mut state.filter_tag, Some(tag), label);
+                }
+            });
+    }
+}
+
+/// Count and base-currency total per tag of the filtered transactions; a tag's link filters by it.
+fn draw_tag_totals(ui: &mut egui::Ui, state: &mut AppState) {
+    let totals = {
+        let rows = state.table_rows();
+        let filtered: Vec<&Transaction> = rows.iter().map(|&i| &state.data.transactions[i]).collect();
+        state.tag_totals(&filtered)
+    };
+    if !totals.is_empty() {
+        ui.separator();
+        ui.heading("Tag Totals");
+        egui::Grid::new("tag_totals").striped(true).show(ui, |ui| {
+            for (tag, (count, total)) in totals {
+                if ui.link(format!("#{tag}")).on_hover_text("Show only this tag").clicked() {
+                    state.filter_tag = Some(tag);
+                }
+                ui.label(count.to_string());
+                ui.label(format!("{total:.2} {}", state.base_currency));
+                ui.end_row();
+            }
+        });
+    }
+}
+
+/// Header label that sorts the table by `column`: ascending, then descending, then back to
+/// insertion order.
+fn sort_header(ui: &mut egui::Ui, state: &mut AppState, label: &str, column: SortColumn) {
+    let active = state.sort_column == Some(column);
+    let arrow = match (active, state.sort_ascending) {
+        (true, true) => " ⏶",
+        (true, false) => " ⏷",
+        _ => "",
+    };
+    let response = ui
+        .add(egui::Label::new(egui::RichText::new(format!("{label}{arrow}")).strong()).sense(egui::Sense::click()))
+        .on_hover_text("Click to sort");
+    if response.clicked() {
+        match (active, state.sort_ascending) {
+            (false, _) => {
+                state.sort_column = Some(column);
+                state.sort_ascending = true;
+            }
+            (true, true) => state.sort_ascending = false,
+            (true, false) => state.sort_column = None,
+        }
+    }
+}
+
+/// `label` with the icon and colour set for `category` in the category manager.
+fn category_text(state: &AppState, category: &str, label: &str) -> egui::RichText {
+    match state.category_style(category) {
+        Some(style) => {
+            let text = egui::RichText::new(format!("{} {label}", style.icon).trim_start().to_string());
+            match style.color {
+                Some([r, g, b]) => text.color(egui::Color32::from_rgb(r, g, b)),
+                None => text,
+            }
+        }
+        None => egui::RichText::new(label),
+    }
+}
+
+/// Lists the category tree with counts, totals and budgets. The selected category can be
+/// restyled, renamed, merged into another or deleted with its transactions reassigned.
+fn draw_category_manager(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_category_manager;
+    egui::Window::new("Categories")
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut state.new_category_input).hint_text("Parent > New category"));
+                if ui.button("Add").clicked() {
+                    let name = state.new_category_input.clone();
+                    if let Some(name) = state.add_category(&name) {
+                        state.save();
+                        state.new_category_input.clear();
+                        state.rates_api_error = Some(format!("Category '{name}' added."));
+                    }
+                }
+            });
+            ui.separator();
+
+            let categories = state.categories();
+            let stats = state.category_stats();
+            egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                egui::Grid::new("category_grid").striped(true).num_columns(4).show(ui, |ui| {
+                    ui.strong("Category");
+                    ui.strong("Transactions");
+                    ui.strong(format!("Total ({})", state.base_currency));
+                    ui.strong("Budget");
+                    ui.end_row();
+                    for cat in &categories {
+                        let (count, total) = stats.get(cat).copied().unwrap_or_default();
+                        let selected = state.category_editing.as_ref() == Some(cat);
+                        ui.horizontal(|ui| {
+                            ui.add_space(category_depth(cat) as f32 * 12.0);
+                            let text = category_text(state, cat, category_leaf(cat));
+                            if ui.selectable_label(selected, text).on_hover_text(cat).clicked() {
+                                state.category_editing = Some(cat.clone());
+                                state.category_name_input = cat.clone();
+                                state.category_target = None;
+                            }
+                        });
+                        ui.label(count.to_string());
+                        ui.label(format!("{total:.2}"));
+                        match state.data.budget.limits.get(cat) {
+                            Some(limit) => ui.label(format!("{:.2} {}, {}", limit.amount, limit.currency, limit.period_label())),
+                            None => ui.label(""),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+
+            let Some(editing) = state.category_editing.clone().filter(|c| categories.contains(c)) else {
+                ui.separator();
+                ui.label("Select a category to edit it.");
+                return;
+            };
+            ui.separator();
+            ui.heading(&editing);
+
+            let mut style = state.data.category_styles.get(&editing).cloned().unwrap_or_default();
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                let mut has_color = style.color.is_some();
+                changed |= ui.checkbox(&mut has_color, "Colour").changed();
+                let mut rgb = style.color.unwrap_or([90, 120, 200]);
+                if has_color {
+                    changed |= ui.color_edit_button_srgb(&mut rgb).changed();
+                }
+                style.color = has_color.then_some(rgb);
+                ui.label("Icon:");
+                changed |= ui.add(egui::TextEdit::singleline(&mut style.icon).desired_width(40.0)).changed();
+            });
+            if changed {
+                if style.color.is_none() && style.icon.trim().is_empty() {
+                    state.data.category_styles.remove(&editing);
+                } else {
+                    state.data.category_styles.insert(editing.clone(), style);
+                }
+                state.save();
+            }
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.category_name_input);
+                if ui.button("Rename").clicked() {
+                    let to = normalize_category(&state.category_name_input);
+                    if to.is_empty() || to == editing || is_within(&to, &editing) {
+                        state.rates_api_error = Some(format!("Cannot rename '{editing}' to '{to}'."));
+                    } else {
+                        let merged = categories.contains(&to);
+                        let count = state.rename_category(&editing, &to);
+                        state.save();
+                        state.category_editing = Some(to.clone());
+                        let verb = if merged { "merged into" } else { "renamed to" };
+                        state.rates_api_error = Some(format!("'{editing}' {verb} '{to}' ({count} transactions)."));
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("category_target")
+                    .selected_text(state.category_target.clone().unwrap_or_else(|| "Choose category".to_string()))
+                    .show_ui(ui, |ui| {
+                        for cat in categories.iter().filter(|c| !is_within(c, &editing)) {
+                            ui.selectable_value(&mut state.category_target, Some(cat.clone()), cat);
+                        }
+                    });
+                let target = state.category_target.clone();
+                if ui.add_enabled(target.is_some(), egui::Button::new("Merge into")).clicked() {
+                    let target = target.clone().unwrap_or_default();
+                    let count = state.rename_category(&editing, &target);
+                    state.save();
+                    state.category_editing = Some(target.clone());
+                    state.rates_api_error = Some(format!("'{editing}' merged into '{target}' ({count} transactions)."));
+                }
+                let delete = ui
+                    .button("Delete")
+                    .on_hover_text("Moves its transactions to the chosen category, or to Uncategorized");
+                if delete.clicked() {
+                    let target = target.unwrap_or_default();
+                    let count = state.delete_category(&editing, &target);
+                    state.save();
+                    state.category_editing = None;
+                    state.rates_api_error = Some(format!("'{editing}' deleted, {count} transactions reassigned."));
+                }
+            });
+        });
+    state.show_category_manager = open;
+}
+
+/// The auto-categorisation rules in the order they are tried, a form for adding one, and a
+/// command to re-run them over existing transactions.
+fn draw_rules(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_rules;
+    egui::Window::new("Rules")
+        .open(&mut open)
+        .default_width(560.0)
+        .show(ctx, |ui| {
+            ui.label("The first matching rule sets the category and adds tags, on add and on import. A category typed when adding wins over the rule's.");
+            ui.separator();
+            let mut action = None;
+            let count = state.data.rules.len();
+            for (i, rule) in state.data.rules.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(i > 0, egui::Button::new("⏶")).clicked() {
+                        action = Some((i, -1));
+                    }
+                    if ui.add_enabled(i + 1 < count, egui::Button::new("⏷")).clicked() {
+                        action = Some((i, 1));
+                    }
+                    if ui.button("🗑").clicked() {
+                        action = Some((i, 0));
+                    }
+                    ui.label(rule.summary());
+                });
+            }
+            if count == 0 {
+                ui.label("No rules yet.");
+            }
+            if let Some((i, step)) = action {
+                match step {
+                    0 => {
+                        state.data.rules.remove(i);
+                    }
+                    _ => state.data.rules.swap(i, (i as isize + step) as usize),
+                }
+                state.save();
+            }
+
+            ui.separator();
+            ui.strong("New rule");
+            egui::Grid::new("rule_form").num_columns(2).show(ui, |ui| {
+                ui.label("Description:");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut state.rule_pattern);
+                    ui.checkbox(&mut state.rule_regex, "Regex");
+                });
+                ui.end_row();
+                ui.label("Amount:");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.rule_min).hint_text("min").desired_width(70.0));
+                    ui.label("to");
+                    ui.add(egui::TextEdit::singleline(&mut state.rule_max).hint_text("max").desired_width(70.0));
+                });
+                ui.end_row();
+                ui.label("Currency:");
+                egui::ComboBox::from_id_source("rule_currency")
+                    .selected_text(state.rule_currency.map(|c| c.as_str()).unwrap_or("Any"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.rule_currency, None, "Any");
+                        for &c in Currency::all() {
+                            ui.selectable_value(&mut state.rule_currency, Some(c), c.as_str());
+                        }
+                    });
+                ui.end_row();
+                ui.label("Account:");
+                ui.text_edit_singleline(&mut state.rule_account);
+                ui.end_row();
+                ui.label("Set category:");
+                ui.text_edit_singleline(&mut state.rule_category);
+                ui.end_row();
+                ui.label("Add tags:");
+                ui.add(egui::TextEdit::singleline(&mut state.rule_tags).hint_text("comma-separated"));
+                ui.end_row();
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Add rule").clicked() {
+                    let parse = |s: &str| -> Result<Option<f64>, String> {
+                        let s = s.trim();
+                        if s.is_empty() { Ok(None) } else { s.parse().map(Some).map_err(|_| format!("'{s}' is not an amount")) }
+                    };
+                    let rule = parse(&state.rule_min).and_then(|min| {
+                        let rule = CategoryRule {
+                            pattern: state.rule_pattern.trim().to_string(),
+                            match_kind: if state.rule_regex { MatchKind::Regex } else { MatchKind::Contains },
+                            min_amount: min,
+                            max_amount: parse(&state.rule_max)?,
+                            currency: state.rule_currency,
+                            account: state.rule_account.trim().to_string(),
+                            category: normalize_category(&state.rule_category),
+                            tags: parse_tags(&state.rule_tags),
+                        };
+                        rule.validate().map(|_| rule)
+                    });
+                    match rule {
+                        Ok(rule) => {
+                            state.data.rules.push(rule);
+                            state.save();
+                            state.rule_pattern.clear();
+                            state.rule_min.clear();
+                            state.rule_max.clear();
+                            state.rule_account.clear();
+                            state.rule_category.clear();
+                            state.rule_tags.clear();
+                            state.rates_api_error = Some("Rule added.".to_string());
+                        }
+                        Err(e) => state.rates_api_error = Some(format!("Rule not added: {e}")),
+                    }
+                }
+                if ui.button("Re-apply rules to all transactions").clicked() {
+                    let changed = state.reapply_rules();
+                    state.save();
+                    state.rates_api_error = Some(format!("Rules updated {changed} transaction(s)."));
+                }
+            });
+        });
+    state.show_rules = open;
+}
+
+/// Budget against actual spending for the last twelve months, each month measured against the
+/// limits in force at the time, and every change of limit.
+fn draw_budget_history(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_budget_history;
+    egui::Window::new("Budget History")
+        .open(&mut open)
+        .default_width(640.0)
+        .show(ctx, |ui| {
+            let today = chrono::Local::now().date_naive();
+            let months: Vec<(i32, u32)> = (0..12)
+                .rev()
+                .map(|back| {
+                    let index = today.year() * 12 + today.month0() as i32 - back;
+                    (index.div_euclid(12), index.rem_euclid(12) as u32 + 1)
+                })
+                .collect();
+            let rows = state.budget_vs_actual(&months);
+            if state.data.budget.limits.is_empty() {
+                ui.label("No budgets set yet.");
+                return;
+            }
+            ui.label(format!("Spent of budget per month, in {}. Red cells are over budget.", state.base_currency));
+            egui::ScrollArea::both().max_height(320.0).show(ui, |ui| {
+                egui::Grid::new("budget_history").striped(true).show(ui, |ui| {
+                    ui.strong("Month");
+                    for cell in rows.first().map(|r| r.1.as_slice()).unwrap_or_default() {
+                        ui.strong(category_leaf(&cell.category)).on_hover_text(&cell.category);
+                    }
+                    ui.end_row();
+                    for ((year, month), row) in rows.iter().rev() {
+                        ui.label(format!("{year}-{month:02}"));
+                        for cell in row {
+                            match cell.budget {
+                                Some(budget) => {
+                                    let color = if cell.actual > budget { egui::Color32::RED } else { ui.visuals().text_color() };
+                                    ui.colored_label(color, format!("{:.2} / {:.2}", cell.actual, budget));
+                                }
+                                None => {
+                                    ui.label(format!("{:.2} / –", cell.actual));
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+            ui.separator();
+            ui.heading("Changes");
+            let mut categories: Vec<&String> = state.data.budget.limits.keys().collect();
+            categories.sort();
+            egui::ScrollArea::vertical().id_source("budget_changes").max_height(200.0).show(ui, |ui| {
+                egui::Grid::new("budget_changes_grid").striped(true).show(ui, |ui| {
+                    for heading in ["Category", "From", "Limit", "Period"] {
+                        ui.strong(heading);
+                    }
+                    ui.end_row();
+                    for category in categories {
+                        for limit in state.data.budget.versions(category) {
+                            ui.label(category.as_str());
+                            ui.label(limit.effective.map_or("start".to_string(), |d| d.to_string()));
+                            ui.label(format!("{:.2} {}", limit.amount, limit.currency));
+                            ui.label(limit.period_label());
+                            ui.end_row();
+                        }
+                    }
+                });
+            });
+        });
+    state.show_budget_history = open;
+}
+
+/// Savings goals with their progress, the monthly contribution they need and when they will be
+/// reached at the current pace, and a form for adding or editing one.
+fn draw_goals(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_goals;
+    egui::Window::new("Goals")
+        .open(&mut open)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            let mut action = None;
+            for (i, goal) in state.data.goals.iter().enumerate() {
+                let progress = state.goal_progress(goal);
+                ui.horizontal(|ui| {
+                    ui.strong(&goal.name);
+                    if let Some(date) = goal.target_date {
+                        ui.label(format!("by {date}"));
+                    }
+                    if ui.small_button("Edit").clicked() {
+                        action = Some((i, false));
+                    }
+                    if ui.small_button("✖").on_hover_text("Delete goal").clicked() {
+                        action = Some((i, true));
+                    }
+                });
+                let bar = egui::ProgressBar::new(progress.fraction(goal) as f32)
+                    .text(format!("{:.2} of {:.2} {}", progress.saved, goal.target, goal.currency))
+                    .desired_width(320.0);
+                let bar = if progress.is_reached(goal) { bar.fill(egui::Color32::from_rgb(60, 160, 60)) } else { bar };
+                ui.add(bar);
+                if progress.is_reached(goal) {
+                    ui.label("Reached!");
+                } else {
+                    let mut pace = format!("Saving {:.2} a month", progress.monthly_rate);
+                    match progress.projected {
+                        Some(date) => pace.push_str(&format!(", reached around {date}")),
+                        None => pace.push_str(", no completion date at this pace"),
+                    }
+                    ui.label(pace);
+                    if let Some(needed) = progress.monthly_needed {
+                        let late = progress.projected.zip(goal.target_date).is_none_or(|(p, t)| p > t);
+                        let color = if late { egui::Color32::from_rgb(200, 120, 40) } else { ui.visuals().text_color() };
+                        ui.colored_label(color, format!("Needs {needed:.2} {} a month to finish on time", goal.currency));
+                    }
+                }
+                ui.separator();
+            }
+            if state.data.goals.is_empty() {
+                ui.label("No goals yet.");
+                ui.separator();
+            }
+            match action {
+                Some((i, true)) => {
+                    state.data.goals.remove(i);
+                    state.goal_editing = None;
+                    state.save();
+                }
+                Some((i, false)) => {
+                    let goal = state.data.goals[i].clone();
+                    state.goal_editing = Some(i);
+                    state.goal_name = goal.name;
+                    state.goal_target = goal.target.to_string();
+                    state.goal_currency = goal.currency;
+                    state.goal_date = goal.target_date.map(|d| d.to_string()).unwrap_or_default();
+                    state.goal_category = goal.category;
+                    state.goal_account = goal.account;
+                    state.goal_tag = goal.tag;
+                    state.goal_initial = if goal.initial == 0.0 { String::new() } else { goal.initial.to_string() };
+                }
+                None => {}
+            }
+
+            ui.strong(if state.goal_editing.is_some() { "Edit goal" } else { "New goal" });
+            egui::Grid::new("goal_form").num_columns(2).show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut state.goal_name);
+                ui.end_row();
+                ui.label("Target:");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.goal_target).desired_width(90.0));
+                    egui::ComboBox::from_id_source("goal_currency")
+                        .selected_text(state.goal_currency.as_str())
+                        .show_ui(ui, |ui| {
+                            for &c in Currency::all() {
+                                ui.selectable_value(&mut state.goal_currency, c, c.as_str());
+                            }
+                        });
+                });
+                ui.end_row();
+                ui.label("By:");
+                ui.add(egui::TextEdit::singleline(&mut state.goal_date).hint_text("YYYY-MM-DD (optional)"));
+                ui.end_row();
+                ui.label("Category:");
+                let categories = state.categories();
+                autocomplete(ui, "goal_category", &mut state.goal_category, &categories);
+                ui.end_row();
+                ui.label("Savings account:");
+                ui.text_edit_singleline(&mut state.goal_account);
+                ui.end_row();
+                ui.label("Tag:");
+                ui.text_edit_singleline(&mut state.goal_tag);
+                ui.end_row();
+                ui.label("Already saved:");
+                ui.add(egui::TextEdit::singleline(&mut state.goal_initial).hint_text("0"));
+                ui.end_row();
+            });
+            ui.label("Spending in the category, money coming into the account, and transactions with the tag count as saved.");
+            ui.horizontal(|ui| {
+                if ui.button(if state.goal_editing.is_some() { "Save goal" } else { "Add goal" }).clicked() {
+                    match goal_from_form(state) {
+                        Ok(goal) => {
+                            match state.goal_editing.take().filter(|&i| i < state.data.goals.len()) {
+                                Some(i) => state.data.goals[i] = goal,
+                                None => state.data.goals.push(goal),
+                            }
+                            state.save();
+                            clear_goal_form(state);
+                            state.rates_api_error = Some("Goal saved.".to_string());
+                        }
+                        Err(e) => state.rates_api_error = Some(format!("Goal not saved: {e}")),
+                    }
+                }
+                if state.goal_editing.is_some() && ui.button("Cancel").clicked() {
+                    state.goal_editing = None;
+                    clear_goal_form(state);
+                }
+            });
+        });
+    state.show_goals = open;
+}
+
+fn goal_from_form(state: &AppState) -> Result<SavingsGoal, String> {
+    let target = state.goal_target.trim().parse().map_err(|_| format!("'{}' is not an amount", state.goal_target))?;
+    let initial = match state.goal_initial.trim() {
+        "" => 0.0,
+        s => s.parse().map_err(|_| format!("'{s}' is not an amount"))?,
+    };
+    let target_date = match state.goal_date.trim() {
+        "" => None,
+        s => Some(chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| format!("'{s}' is not a date"))?),
+    };
+    let goal = SavingsGoal {
+        name: state.goal_name.trim().to_string(),
+        target,
+        currency: state.goal_currency,
+        target_date,
+        category: normalize_category(&state.goal_category),
+        account: state.goal_account.trim().to_string(),
+        tag: crate::tags::normalize_tag(&state.goal_tag),
+        initial,
+    };
+    goal.validate().map(|_| goal)
+}
+
+fn clear_goal_form(state: &mut AppState) {
+    state.goal_name.clear();
+    state.goal_target.clear();
+    state.goal_date.clear();
+    state.goal_category.clear();
+    state.goal_account.clear();
+    state.goal_tag.clear();
+    state.goal_initial.clear();
+}
+
+/// Loans with their installment, remaining balance and payoff date, the amortization schedule
+/// of one of them, and a form for adding one.
+fn draw_loans(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_loans;
+    egui::Window::new("Loans")
+        .open(&mut open)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            let mut action = None;
+            for (i, loan) in state.data.loans.iter().enumerate() {
+                let status = state.loan_status(loan);
+                let currency = loan.currency;
+                ui.horizontal(|ui| {
+                    ui.strong(&loan.name);
+                    ui.label(format!(
+                        "{:.2} {currency} at {}% over {} months, {:.2} a month",
+                        loan.principal,
+                        loan.rate,
+                        loan.term_months,
+                        loan.payment()
+                    ));
+                });
+                let paid = if loan.principal > 0.0 { 1.0 - status.balance / loan.principal } else { 1.0 };
+                ui.add(
+                    egui::ProgressBar::new(paid.clamp(0.0, 1.0) as f32)
+                        .text(format!("{:.2} {currency} left", status.balance))
+                        .desired_width(320.0),
+                );
+                ui.label(format!(
+                    "Paid {:.2} principal and {:.2} interest. {}",
+                    status.paid_principal,
+                    status.paid_interest,
+                    match status.payoff {
+                        Some(date) => format!("Paid off {date}."),
+                        None => "The installment doesn't cover the interest.".to_string(),
+                    }
+                ));
+                ui.horizontal(|ui| {
+                    let showing = state.loan_schedule == Some(i);
+                    if ui.selectable_label(showing, "Schedule").clicked() {
+                        action = Some((i, if showing { "hide" } else { "schedule" }));
+                    }
+                    if ui.button("Split payments").on_hover_text(format!("Split payments in {} into principal and interest", loan.category)).clicked() {
+                        action = Some((i, "split"));
+                    }
+                    if ui.small_button("✖").on_hover_text("Delete loan").clicked() {
+                        action = Some((i, "delete"));
+                    }
+                });
+                if state.loan_schedule == Some(i) {
+                    egui::ScrollArea::vertical().id_source(format!("loan_schedule_{i}")).max_height(240.0).show(ui, |ui| {
+                        egui::Grid::new(format!("loan_schedule_grid_{i}")).striped(true).show(ui, |ui| {
+                            for heading in ["#", "Due", "Payment", "Interest", "Principal", "Balance"] {
+                                ui.strong(heading);
+                            }
+                            ui.end_row();
+                            for row in loan.schedule() {
+                                ui.label(row.number.to_string());
+                                ui.label(row.date.to_string());
+                                ui.label(format!("{:.2}", row.payment));
+                                ui.label(format!("{:.2}", row.interest));
+                                ui.label(format!("{:.2}", row.principal));
+                                ui.label(format!("{:.2}", row.balance));
+                                ui.end_row();
                             }
                         });
                     });
                 }
+                ui.separator();
+            }
+            if state.data.loans.is_empty() {
+                ui.label("No loans yet.");
+                ui.separator();
+            }
+            match action {
+                Some((i, "schedule")) => state.loan_schedule = Some(i),
+                Some((_, "hide")) => state.loan_schedule = None,
+                Some((i, "split")) => {
+                    let loan = state.data.loans[i].clone();
+                    let split = state.split_loan_payments(&loan);
+                    state.save();
+                    state.rates_api_error = Some(format!("Split {split} payment(s) of {}.", loan.name));
+                }
+                Some((i, "delete")) => {
+                    state.data.loans.remove(i);
+                    state.loan_schedule = None;
+                    state.save();
+                }
+                _ => {}
+            }
+
+            ui.strong("New loan");
+            egui::Grid::new("loan_form").num_columns(2).show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut state.loan_name);
+                ui.end_row();
+                ui.label("Principal:");
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut state.loan_principal).desired_width(90.0));
+                    egui::ComboBox::from_id_source("loan_currency")
+                        .selected_text(state.loan_currency.as_str())
+                        .show_ui(ui, |ui| {
+                            for &c in Currency::all() {
+                                ui.selectable_value(&mut state.loan_currency, c, c.as_str());
+                            }
+                        });
+                });
+                ui.end_row();
+                ui.label("Interest rate (%):");
+                ui.add(egui::TextEdit::singleline(&mut state.loan_rate).desired_width(60.0));
+                ui.end_row();
+                ui.label("Term (months):");
+                ui.add(egui::TextEdit::singleline(&mut state.loan_term).desired_width(60.0));
+                ui.end_row();
+                ui.label("First payment:");
+                ui.add(egui::TextEdit::singleline(&mut state.loan_start).hint_text("YYYY-MM-DD"));
+                ui.end_row();
+                ui.label("Payment category:");
+                let categories = state.categories();
+                autocomplete(ui, "loan_category", &mut state.loan_category, &categories);
+                ui.end_row();
+            });
+            ui.label("Payments are the transactions in the payment category. Leave it empty to use Loans > name.");
+            if ui.button("Add loan").clicked() {
+                match loan_from_form(state) {
+                    Ok(loan) => {
+                        state.data.loans.push(loan);
+                        state.save();
+                        state.loan_name.clear();
+                        state.loan_principal.clear();
+                        state.loan_rate.clear();
+                        state.loan_term.clear();
+                        state.loan_start.clear();
+                        state.loan_category.clear();
+                        state.rates_api_error = Some("Loan added.".to_string());
+                    }
+                    Err(e) => state.rates_api_error = Some(format!("Loan not added: {e}")),
+                }
+            }
+        });
+    state.show_loans = open;
+}
+
+fn loan_from_form(state: &AppState) -> Result<Loan, String> {
+    let number = |s: &str| s.trim().parse::<f64>().map_err(|_| format!("'{}' is not a number", s.trim()));
+    let name = state.loan_name.trim().to_string();
+    let category = match normalize_category(&state.loan_category) {
+        c if c.is_empty() => normalize_category(&format!("Loans > {name}")),
+        c => c,
+    };
+    let loan = Loan {
+        principal: number(&state.loan_principal)?,
+        rate: number(&state.loan_rate)?,
+        term_months: state.loan_term.trim().parse().map_err(|_| format!("'{}' is not a number of months", state.loan_term.trim()))?,
+        currency: state.loan_currency,
+        start: chrono::NaiveDate::parse_from_str(state.loan_start.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("'{}' is not a date", state.loan_start.trim()))?,
+        category,
+        name,
+    };
+    loan.validate().map(|_| loan)
+}
+
+fn draw_forecast(ctx: &egui::Context, state: &mut AppState) {
+    let mut open = state.show_forecast;
+    egui::Window::new("Cash-Flow Forecast")
+        .open(&mut open)
+        .default_width(640.0)
+        .show(ctx, |ui| {
+            let base = state.base_currency;
+            ui.horizontal(|ui| {
+                ui.label("Months ahead:");
+                ui.add(egui::DragValue::new(&mut state.forecast_months).clamp_range(1..=24));
             });
+            ui.collapsing(format!("Current balances ({base})"), |ui| {
+                let mut changed = false;
+                egui::Grid::new("forecast_balances").num_columns(2).show(ui, |ui| {
+                    for account in state.accounts() {
+                        ui.label(&account);
+                        let balance = state.data.account_balances.entry(account).or_insert(0.0);
+                        changed |= ui.add(egui::DragValue::new(balance).speed(10.0).fixed_decimals(2)).changed();
+                        ui.end_row();
+                    }
+                });
+                if changed {
+                    state.save();
+                }
+            });
+
+            let forecast = state.forecast(Local::now().date_naive(), state.forecast_months);
+            for (account, date, balance) in forecast.warnings() {
+                ui.colored_label(egui::Color32::RED, format!("⚠ {account} would go negative on {date} ({balance:.2} {base})"));
+            }
+            draw_forecast_chart(ui, &forecast);
+
+            ui.collapsing(format!("Expected items ({})", forecast.items.len()), |ui| {
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    egui::Grid::new("forecast_items").striped(true).show(ui, |ui| {
+                        for heading in ["Date", "Source", "Account", "Description", "Amount"] {
+                            ui.strong(heading);
+                        }
+                        ui.end_row();
+                        for item in &forecast.items {
+                            ui.label(item.date.to_string());
+                            ui.label(item.source.as_str());
+                            ui.label(&item.account);
+                            ui.label(&item.description);
+                            ui.label(format!("{:.2}", -item.amount));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+            ui.label("Recurring transactions repeat at their usual interval; budgets are assumed to be spent in full at the start of each period.");
+        });
+    state.show_forecast = open;
+}
+
+/// Each account's projected balance as a step line, with the zero line in red.
+fn draw_forecast_chart(ui: &mut egui::Ui, forecast: &crate::forecast::Forecast) {
+    const COLORS: [egui::Color32; 6] = [
+        egui::Color32::from_rgb(66, 133, 244),
+        egui::Color32::from_rgb(52, 168, 83),
+        egui::Color32::from_rgb(251, 188, 5),
+        egui::Color32::from_rgb(171, 71, 188),
+        egui::Color32::from_rgb(0, 172, 193),
+        egui::Color32::from_rgb(255, 112, 67),
+    ];
+    let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), 220.0), egui::Sense::hover());
+    let rect = response.rect.shrink(8.0);
+    painter.rect_stroke(response.rect, 2.0, ui.visuals().widgets.noninteractive.bg_stroke);
+    let values = forecast.balances.values().flatten().map(|p| p.1);
+    let (low, high) = values.fold((0.0f64, 0.0f64), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    let span = if high - low < 1.0 { 1.0 } else { high - low };
+    let days = (forecast.end - forecast.start).num_days().max(1) as f32;
+    let x = |date: chrono::NaiveDate| rect.left() + rect.width() * (date - forecast.start).num_days() as f32 / days;
+    let y = |balance: f64| rect.bottom() - rect.height() * ((balance - low) / span) as f32;
+    painter.line_segment([egui::pos2(rect.left(), y(0.0)), egui::pos2(rect.right(), y(0.0))], (1.0, egui::Color32::RED));
+
+    let text_color = ui.visuals().text_color();
+    let font = egui::FontId::proportional(11.0);
+    painter.text(rect.left_top(), egui::Align2::LEFT_TOP, format!("{high:.0}"), font.clone(), text_color);
+    painter.text(rect.left_bottom(), egui::Align2::LEFT_BOTTOM, format!("{low:.0}"), font.clone(), text_color);
+    painter.text(rect.center_bottom(), egui::Align2::CENTER_BOTTOM, format!("{} – {}", forecast.start, forecast.end), font, text_color);
+
+    for (i, (account, points)) in forecast.balances.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let mut line = Vec::new();
+        for (j, &(date, balance)) in points.iter().enumerate() {
+            if j > 0 {
+                line.push(egui::pos2(x(date), y(points[j - 1].1)));
+            }
+            line.push(egui::pos2(x(date), y(balance)));
+        }
+        if let Some(&(_, last)) = points.last() {
+            line.push(egui::pos2(rect.right(), y(last)));
+        }
+        painter.add(egui::Shape::line(line, (2.0, color)));
+        ui.colored_label(color, format!("━ {account}"));
+    }
+}
+
+/// A single-line text field with a dropdown of `options` while it has focus; picking one
+/// replaces the text.
+fn autocomplete(ui: &mut egui::Ui, id: &str, text: &mut String, options: &[String]) {
+    let response = ui.text_edit_singleline(text);
+    let popup_id = ui.make_persistent_id(id);
+    if response.has_focus() && !options.is_empty() {
+        ui.memory_mut(|m| m.open_popup(popup_id));
+    }
+    egui::popup::popup_below_widget(ui, popup_id, &response, |ui| {
+        ui.set_min_width(response.rect.width());
+        for option in options {
+            if ui.selectable_label(false, option).clicked() {
+                *text = option.clone();
+                ui.memory_mut(|m| m.close_popup());
+            }
+        }
     });
-}
\ No newline at end of file
+}
@@ -0,0 +1,311 @@
+use crate::categories::{is_within, CATEGORY_SEPARATOR};
+use crate::types::*;
+use chrono::{Months, NaiveDate};
+
+/// Balances below this are paid off.
+const LOAN_TOLERANCE: f64 = 0.005;
+/// Longest payoff projected, in months.
+const MAX_PROJECTION: u32 = 1200;
+
+/// One row of an amortization schedule, in the loan's currency.
+pub struct Installment {
+    pub number: u32,
+    pub date: NaiveDate,
+    pub payment: f64,
+    pub interest: f64,
+    pub principal: f64,
+    /// Owed after this installment.
+    pub balance: f64,
+}
+
+/// What has been repaid so far according to the recorded payments, in the loan's currency.
+pub struct LoanStatus {
+    pub paid_principal: f64,
+    pub paid_interest: f64,
+    pub balance: f64,
+    /// When the last installment falls due if the scheduled payment is kept up; `None` if it
+    /// doesn't cover the interest.
+    pub payoff: Option<NaiveDate>,
+}
+
+/// A payment divided into principal and interest, in the transaction's currency.
+struct PaymentShare {
+    index: usize,
+    principal: f64,
+    interest: f64,
+    /// Loan currency to transaction currency.
+    fx: f64,
+    /// Already split, or filed under the principal or interest subcategory.
+    settled: bool,
+}
+
+impl Loan {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("a loan needs a name".to_string());
+        }
+        if !self.principal.is_finite() || self.principal <= 0.0 {
+            return Err("the principal must be positive".to_string());
+        }
+        if !self.rate.is_finite() || self.rate < 0.0 {
+            return Err("the interest rate must be zero or more".to_string());
+        }
+        if self.term_months == 0 {
+            return Err("the term must be at least one month".to_string());
+        }
+        if self.category.is_empty() {
+            return Err("a loan needs a category for its payments".to_string());
+        }
+        Ok(())
+    }
+
+    fn monthly_rate(&self) -> f64 {
+        self.rate / 100.0 / 12.0
+    }
+
+    /// The fixed monthly installment that repays the loan over its term.
+    pub fn payment(&self) -> f64 {
+        let r = self.monthly_rate();
+        let n = self.term_months as f64;
+        if r == 0.0 {
+            self.principal / n
+        } else {
+            self.principal * r / (1.0 - (1.0 + r).powf(-n))
+        }
+    }
+
+    /// Date of installment `number`, counting from 1.
+    fn due_date(&self, number: u32) -> Option<NaiveDate> {
+        self.start.checked_add_months(Months::new(number - 1))
+    }
+
+    /// Every installment over the loan's term. The last one pays off what is left.
+    pub fn schedule(&self) -> Vec<Installment> {
+        let (r, payment) = (self.monthly_rate(), self.payment());
+        let mut balance = self.principal;
+        let mut schedule = Vec::new();
+        for number in 1..=self.term_months {
+            let Some(date) = self.due_date(number) else { break };
+            let interest = balance * r;
+            let principal = if number == self.term_months { balance } else { (payment - interest).min(balance) };
+            balance -= principal;
+            schedule.push(Installment { number, date, payment: principal + interest, interest, principal, balance });
+        }
+        schedule
+    }
+
+    pub fn principal_category(&self) -> String {
+        format!("{}{CATEGORY_SEPARATOR}Principal", self.category)
+    }
+
+    pub fn interest_category(&self) -> String {
+        format!("{}{CATEGORY_SEPARATOR}Interest", self.category)
+    }
+}
+
+impl AppState {
+    /// Indices of the loan's payments, oldest first.
+    fn loan_payments(&self, loan: &Loan) -> Vec<usize> {
+        let mut payments: Vec<usize> = (0..self.data.transactions.len())
+            .filter(|&i| self.data.transactions[i].any_category(|c| is_within(c, &loan.category)))
+            .collect();
+        payments.sort_by_key(|&i| self.data.transactions[i].date);
+        payments
+    }
+
+    /// Each of the loan's payments divided into principal and interest, oldest first. Split
+    /// payments and those filed under the principal or interest subcategory are taken as
+    /// recorded. Any other payment is charged a month's interest on the balance left by the
+    /// payments before it, and the rest is principal; refunds are left out.
+    fn loan_payment_shares(&self, loan: &Loan) -> Vec<PaymentShare> {
+        let (principal_category, interest_category) = (loan.principal_category(), loan.interest_category());
+        let mut balance = loan.principal;
+        let mut shares = Vec::new();
+        for index in self.loan_payments(loan) {
+            let tx = &self.data.transactions[index];
+            let fx = self.convert(1.0, loan.currency, tx.currency);
+            let settled = tx.is_split() || is_within(&tx.category, &interest_category) || is_within(&tx.category, &principal_category);
+            // A refund is not a payment; charging it interest would book negative interest
+            if !settled && tx.amount <= 0.0 {
+                continue;
+            }
+            let (principal, interest) = if settled {
+                tx.category_amounts()
+                    .into_iter()
+                    .filter(|(c, _)| is_within(c, &loan.category))
+                    .fold((0.0, 0.0), |(p, i), (c, amount)| {
+                        if is_within(c, &interest_category) { (p, i + amount) } else { (p + amount, i) }
+                    })
+            } else {
+                // Interest in the transaction's currency, rounded to cents
+                let interest = (balance.max(0.0) * loan.monthly_rate() * fx * 100.0).round() / 100.0;
+                // All interest when the payment doesn't cover it
+                if tx.amount - interest > 0.0 { (tx.amount - interest, interest) } else { (0.0, tx.amount) }
+            };
+            balance -= principal / fx;
+            shares.push(PaymentShare { index, principal, interest, fx, settled });
+        }
+        shares
+    }
+
+    /// Repayments so far, the balance left and when it will be paid off. Payments that haven't
+    /// been split yet are divided the way `split_loan_payments` would divide them.
+    pub fn loan_status(&self, loan: &Loan) -> LoanStatus {
+        let (mut paid_principal, mut paid_interest) = (0.0, 0.0);
+        let mut last_payment = None;
+        for share in self.loan_payment_shares(loan) {
+            paid_principal += share.principal / share.fx;
+            paid_interest += share.interest / share.fx;
+            last_payment = Some(self.data.transactions[share.index].date);
+        }
+        let balance = (loan.principal - paid_principal).max(0.0);
+
+        // Keep paying the installment from the first due date after the last payment
+        let (r, payment) = (loan.monthly_rate(), loan.payment());
+        let mut number = 1;
+        while let (Some(last), Some(date)) = (last_payment, loan.due_date(number)) {
+            if date > last || number >= MAX_PROJECTION {
+                break;
+            }
+            number += 1;
+        }
+        let mut remaining = balance;
+        let mut payoff = last_payment.filter(|_| remaining <= LOAN_TOLERANCE);
+        while remaining > LOAN_TOLERANCE && number < MAX_PROJECTION {
+            let interest = remaining * r;
+            if payment <= interest {
+                break;
+            }
+            remaining = (remaining + interest - payment).max(0.0);
+            if remaining <= LOAN_TOLERANCE {
+                payoff = loan.due_date(number);
+            }
+            number += 1;
+        }
+        LoanStatus { paid_principal, paid_interest, balance, payoff }
+    }
+
+    /// Splits the loan's unsplit payments into principal and interest, charging a month's
+    /// interest on the balance left by the payments before each. Returns how many were split.
+    pub fn split_loan_payments(&mut self, loan: &Loan) -> usize {
+        let (principal_category, interest_category) = (loan.principal_category(), loan.interest_category());
+        let mut split = 0;
+        for share in self.loan_payment_shares(loan).into_iter().filter(|s| !s.settled) {
+            if share.principal > 0.0 && share.interest > 0.0 {
+                let lines = vec![
+                    Split { category: principal_category.clone(), amount: share.principal, memo: String::new() },
+                    Split { category: interest_category.clone(), amount: share.interest, memo: String::new() },
+                ];
+                if self.set_splits(share.index, lines).is_err() {
+                    continue;
+                }
+            } else {
+                // All principal without interest, all interest when the payment doesn't cover it
+                let category = if share.principal > 0.0 { &principal_category } else { &interest_category };
+                self.data.transactions[share.index].category = category.clone();
+            }
+            split += 1;
+        }
+        split
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn car_loan(rate: f64) -> Loan {
+        Loan {
+            name: "Car".to_string(),
+            principal: 12000.0,
+            rate,
+            term_months: 42,
+            currency: Currency::USD,
+            start: date(2026, 1, 31),
+            category: "Car Loan".to_string(),
+        }
+    }
+
+    fn with_payments(loan: &Loan, payments: &[(NaiveDate, f64)]) -> AppState {
+        let mut state = AppState::load_or_default(None);
+        for &(day, amount) in payments {
+            state.data.transactions.push(Transaction {
+                date: day,
+                description: "Car payment".to_string(),
+                amount,
+                category: loan.category.clone(),
+                currency: Currency::USD,
+                ..Default::default()
+            });
+        }
+        state
+    }
+
+    #[test]
+    fn payment_follows_the_annuity_formula() {
+        assert!((car_loan(5.5).payment() - 314.748284).abs() < 1e-6);
+        assert_eq!(car_loan(0.0).payment(), 12000.0 / 42.0);
+    }
+
+    #[test]
+    fn schedule_pays_off_exactly() {
+        for rate in [0.0, 5.5, 19.9] {
+            let loan = car_loan(rate);
+            let schedule = loan.schedule();
+            assert_eq!(schedule.len(), 42);
+            assert_eq!(schedule[1].date, date(2026, 2, 28), "due dates clamp to the end of the month");
+            let last = schedule.last().unwrap();
+            assert_eq!(last.balance, 0.0, "{rate}%");
+            let repaid: f64 = schedule.iter().map(|i| i.principal).sum();
+            assert!((repaid - loan.principal).abs() < 1e-6, "{rate}%");
+            // Only the last installment absorbs the rounding
+            assert!((last.payment - loan.payment()).abs() < 0.01, "{rate}%");
+            assert!(schedule[..41].iter().all(|i| (i.payment - loan.payment()).abs() < 1e-9));
+        }
+        assert!(car_loan(0.0).schedule().iter().all(|i| i.interest == 0.0));
+    }
+
+    #[test]
+    fn status_matches_before_and_after_splitting() {
+        let loan = car_loan(5.5);
+        let mut state = with_payments(&loan, &[(date(2026, 1, 31), 300.0), (date(2026, 2, 28), 300.0)]);
+        let before = state.loan_status(&loan);
+        assert!((before.paid_interest - 108.88).abs() < 1e-9);
+        assert!((before.paid_principal - 491.12).abs() < 1e-9);
+
+        assert_eq!(state.split_loan_payments(&loan), 2);
+        let after = state.loan_status(&loan);
+        assert!((after.paid_interest - before.paid_interest).abs() < 1e-9);
+        assert!((after.paid_principal - before.paid_principal).abs() < 1e-9);
+        assert!((after.balance - (12000.0 - 491.12)).abs() < 1e-9);
+        assert_eq!(state.split_loan_payments(&loan), 0, "split payments are left alone");
+    }
+
+    #[test]
+    fn refunds_are_not_booked_as_interest() {
+        let loan = car_loan(5.5);
+        let mut state = with_payments(&loan, &[(date(2026, 1, 31), 300.0), (date(2026, 2, 10), -20.0), (date(2026, 2, 28), 300.0)]);
+        let status = state.loan_status(&loan);
+        assert!((status.paid_interest - 108.88).abs() < 1e-9);
+        assert!((status.paid_principal - 491.12).abs() < 1e-9);
+
+        assert_eq!(state.split_loan_payments(&loan), 2);
+        let refund = &state.data.transactions[1];
+        assert!(!refund.is_split());
+        assert_eq!(refund.category, loan.category);
+    }
+
+    #[test]
+    fn payments_below_the_interest_are_all_interest() {
+        let loan = car_loan(5.5);
+        let mut state = with_payments(&loan, &[(date(2026, 1, 31), 40.0)]);
+        let status = state.loan_status(&loan);
+        assert_eq!((status.paid_principal, status.paid_interest), (0.0, 40.0));
+        assert_eq!(state.split_loan_payments(&loan), 1);
+        assert_eq!(state.data.transactions[0].category, loan.interest_category());
+    }
+}
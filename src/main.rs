@@ -4,8 +4,36 @@ mod types;
 mod utils;
 mod data;
 mod analytics;
+mod bulk;
+mod budget_versions;
+mod categories;
+mod query;
+mod envelopes;
+mod forecast;
+mod goals;
+mod loans;
+mod periods;
+mod rollover;
+mod rules;
+mod splits;
+mod suggest;
+mod tags;
+mod ofx;
+mod camt;
+mod mt940;
+mod ledger;
+mod reports;
+mod cli;
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "Budget Tracking App",
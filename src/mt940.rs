@@ -0,0 +1,266 @@
+use crate::types::*;
+use chrono::{Datelike, NaiveDate};
+use std::str::FromStr;
+
+/// A `:tag:` field of an MT940 message, with continuation lines joined.
+struct Field {
+    line: usize,
+    tag: String,
+    value: String,
+}
+
+fn fields(content: &str) -> Vec<Field> {
+    let mut fields: Vec<Field> = Vec::new();
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim_end();
+        // SWIFT envelope blocks and the message trailer carry no statement data
+        if line.is_empty() || line.starts_with('{') || line.starts_with("-}") || line == "-" {
+            continue;
+        }
+        let tagged = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+        match tagged {
+            Some((tag, value)) => fields.push(Field { line: i + 1, tag: tag.to_string(), value: value.to_string() }),
+            None => {
+                if let Some(last) = fields.last_mut() {
+                    last.value.push('\n');
+                    last.value.push_str(line);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// The entry date `MMDD` in the year that puts it nearest the value date, so a booking on
+/// 31 December for a value date of 2 January falls in the year before.
+fn entry_date(mmdd: &str, value_date: NaiveDate) -> Option<NaiveDate> {
+    let (month, day) = (mmdd.get(..2)?.parse().ok()?, mmdd.get(2..)?.parse().ok()?);
+    let year = value_date.year();
+    [year - 1, year, year + 1]
+        .into_iter()
+        .filter_map(|y| NaiveDate::from_ymd_opt(y, month, day))
+        .min_by_key(|d| (*d - value_date).num_days().abs())
+}
+
+/// Parses the statement line of field `:61:`: value date, optional entry date, debit/credit
+/// mark, amount and references. Returns the booking date (the value date when there is no
+/// entry date), signed amount (positive for debits) and the bank reference.
+fn parse_statement_line(value: &str) -> Result<(NaiveDate, f64, Option<String>), String> {
+    let first = value.lines().next().unwrap_or("");
+    let mut date = first
+        .get(..6)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%y%m%d").ok())
+        .ok_or(format!("invalid value date in '{first}'"))?;
+    let mut rest = &first[6..];
+    if let Some(mmdd) = rest.get(..4).filter(|d| d.chars().all(|c| c.is_ascii_digit())) {
+        date = entry_date(mmdd, date).ok_or(format!("invalid entry date in '{first}'"))?;
+        rest = &rest[4..];
+    }
+    let (debit, rest) = if let Some(r) = rest.strip_prefix("RC") {
+        (true, r)
+    } else if let Some(r) = rest.strip_prefix("RD") {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix('C') {
+        (false, r)
+    } else if let Some(r) = rest.strip_prefix('D') {
+        (true, r)
+    } else {
+        return Err(format!("missing debit/credit mark in '{first}'"));
+    };
+    // Optional funds code (third letter of the currency code)
+    let rest = match rest.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => &rest[1..],
+        _ => rest,
+    };
+    let amount_len = rest.find(|c: char| !(c.is_ascii_digit() || c == ',')).unwrap_or(rest.len());
+    let raw_amount = &rest[..amount_len];
+    let amount: f64 = raw_amount
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| format!("invalid amount '{raw_amount}'"))?;
+    // Transaction type (N/F/S plus three characters), then customer reference, then //bank reference
+    let refs = rest[amount_len..].get(4..).unwrap_or("");
+    let (customer_ref, bank_ref) = match refs.split_once("//") {
+        Some((c, b)) => (c, Some(b)),
+        None => (refs, None),
+    };
+    let reference = bank_ref
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .or(Some(customer_ref.trim()).filter(|r| !r.is_empty() && *r != "NONREF"))
+        .map(str::to_string);
+    Ok((date, if debit { amount } else { -amount }, reference))
+}
+
+/// Extracts the remittance text from field `:86:`. Structured German bank narratives use
+/// `?20`–`?29` for remittance lines and `?32`/`?33` for the counterparty name.
+fn narrative(value: &str) -> String {
+    let joined: String = value.lines().collect();
+    if !joined.contains("?20") && !joined.contains("?32") {
+        return value.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+    }
+    let mut remittance = Vec::new();
+    let mut name = Vec::new();
+    for part in joined.split('?').skip(1) {
+        let code = part.get(..2).unwrap_or("");
+        let text = part.get(2..).unwrap_or("").trim();
+        match code {
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" => remittance.push(text),
+            "32" | "33" => name.push(text),
+            _ => {}
+        }
+    }
+    let remittance = remittance.concat();
+    if remittance.is_empty() { name.concat() } else { remittance }
+}
+
+/// Parses a SWIFT MT940 customer statement.
+pub fn parse_mt940(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut account = String::new();
+    let mut currency: Option<Currency> = None;
+    let mut pending: Option<Transaction> = None;
+
+    for field in fields(content) {
+        if field.tag == "86" {
+            if let Some(tx) = &mut pending {
+                tx.description = narrative(&field.value);
+            }
+            continue;
+        }
+        if let Some(tx) = pending.take() {
+            report.rows.push(ImportRow::new(tx));
+        }
+        match field.tag.as_str() {
+            "20" => currency = None,
+            "25" => account = field.value.trim().to_string(),
+            "60F" | "60M" => {
+                // C/D mark, YYMMDD, currency, amount
+                let ccy = field.value.get(7..10).unwrap_or("");
+                currency = Currency::from_str(ccy).ok();
+                if currency.is_none() {
+                    report.issues.push(ImportIssue {
+                        line: field.line,
+                        field: field.tag.clone(),
+                        reason: format!("unsupported currency '{ccy}'"),
+                    });
+                }
+            }
+            "61" => {
+                let parsed = parse_statement_line(&field.value);
+                match (parsed, currency) {
+                    (Ok((date, amount, external_id)), Some(currency)) => {
+                        let tx = Transaction {
+                            date,
+                            amount,
+                            category: "Uncategorized".to_string(),
+                            currency,
+                            account: account.clone(),
+                            external_id,
+                            ..Default::default()
+                        };
+                        pending = Some(tx);
+                    }
+                    (Err(reason), _) => {
+                        report.issues.push(ImportIssue { line: field.line, field: "61".into(), reason });
+                    }
+                    (Ok(_), None) => {
+                        report.issues.push(ImportIssue {
+                            line: field.line,
+                            field: "61".into(),
+                            reason: "no opening balance (:60F:) with a supported currency".into(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(tx) = pending {
+        report.rows.push(ImportRow::new(tx));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    const STATEMENT: &str = "{1:F01TESTBANKAXXX0000000000}{2:I940TESTBANKXXXXN}{4:
+:20:STARTUMS
+:25:10020030/1234567
+:28C:00001/001
+:60F:C260914EUR1234,56
+:61:2609150915DR12,50NTRFNONREF//REF123
+:86:166?00SEPA-UEBERWEISUNG?20Rent Septem
+?21ber 2026?32Landlord GmbH
+:61:260916C2000,NMSCSALARY
+:86:Salary September
+ bonus included
+:61:260917RC40,00NMSCNONREF
+:86:?32Returned Direct Debit
+:61:260918RD15,00NMSCNONREF
+:86:Card refund reversed
+:62F:C260918EUR3222,06
+-}";
+
+    #[test]
+    fn statement_lines_and_signs() {
+        let report = parse_mt940(STATEMENT);
+        assert!(report.issues.is_empty());
+        let rows: Vec<(NaiveDate, f64, &str)> =
+            report.rows.iter().map(|r| (r.transaction.date, r.transaction.amount, r.transaction.description.as_str())).collect();
+        assert_eq!(
+            rows,
+            [
+                (date(2026, 9, 15), 12.5, "Rent September 2026"),
+                (date(2026, 9, 16), -2000.0, "Salary September bonus included"),
+                // A reversed credit takes money out, a reversed debit puts it back
+                (date(2026, 9, 17), 40.0, "Returned Direct Debit"),
+                (date(2026, 9, 18), -15.0, "Card refund reversed"),
+            ]
+        );
+        let first = &report.rows[0].transaction;
+        assert_eq!((first.account.as_str(), first.currency), ("10020030/1234567", Currency::EUR));
+        assert_eq!(first.external_id.as_deref(), Some("REF123"));
+        assert_eq!(report.rows[1].transaction.external_id.as_deref(), Some("SALARY"));
+        assert_eq!(report.rows[2].transaction.external_id, None);
+    }
+
+    #[test]
+    fn entry_date_across_new_year() {
+        // Value date in December, booked in January
+        let (booked, amount, _) = parse_statement_line("2612310102D10,00NTRFNONREF").unwrap();
+        assert_eq!((booked, amount), (date(2027, 1, 2), 10.0));
+        // Value date in January, booked in December
+        let (booked, _, _) = parse_statement_line("2701021231C10,00NTRFNONREF").unwrap();
+        assert_eq!(booked, date(2026, 12, 31));
+        // Without an entry date the value date is used
+        let (booked, _, _) = parse_statement_line("261001C10,00NTRFNONREF").unwrap();
+        assert_eq!(booked, date(2026, 10, 1));
+    }
+
+    #[test]
+    fn invalid_statement_lines() {
+        assert!(parse_statement_line("2609151302D1,00NTRF").is_err());
+        assert!(parse_statement_line("260915X1,00NTRF").is_err());
+        assert!(parse_statement_line("26091X").is_err());
+        let report = parse_mt940(":20:X\n:61:260915D1,00NTRFNONREF\n");
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.rows.is_empty());
+    }
+
+    #[test]
+    fn structured_narrative() {
+        assert_eq!(narrative("166?00GUTSCHRIFT?20Invoice 4?212?32ACME"), "Invoice 42");
+        assert_eq!(narrative("166?00GUTSCHRIFT?32ACME Co?33rp"), "ACME Corp");
+        assert_eq!(narrative("Plain text\n  continued"), "Plain text continued");
+    }
+}
@@ -0,0 +1,312 @@
+use crate::types::*;
+use chrono::NaiveDate;
+use std::str::FromStr;
+
+/// One element of an OFX document. OFX 1.x is SGML where leaf elements have no closing tag,
+/// OFX 2.x is XML; both reduce to the same stream of open tags (with text) and close tags.
+enum Token<'a> {
+    Open { name: String, text: &'a str, line: usize },
+    Close(String),
+}
+
+fn tokenize(content: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        line += rest[..start].matches('\n').count();
+        let Some(end) = rest[start..].find('>') else { break };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let text = rest[..text_end].trim();
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_uppercase()));
+        } else if !tag.starts_with('?') && !tag.starts_with('!') {
+            let name = tag.split_whitespace().next().unwrap_or("").trim_end_matches('/').to_uppercase();
+            tokens.push(Token::Open { name, text, line });
+        }
+    }
+    tokens
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[derive(Default)]
+struct PendingTx {
+    line: usize,
+    date: Option<String>,
+    amount: Option<String>,
+    fitid: Option<String>,
+    name: Option<String>,
+    memo: Option<String>,
+    currency: Option<String>,
+}
+
+/// Parses an OFX/QFX statement. Debits (negative `TRNAMT`) become positive spending amounts.
+pub fn parse_ofx(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut statement_currency: Option<String> = None;
+    let mut account = String::new();
+    let mut pending: Option<PendingTx> = None;
+    let mut in_account = false;
+    // CURSYM counts only in <CURRENCY>; with <ORIGCURRENCY> TRNAMT is still in CURDEF
+    let mut in_currency = false;
+
+    for token in tokenize(content) {
+        match token {
+            Token::Open { name, text, line } => match name.as_str() {
+                "BANKACCTFROM" | "CCACCTFROM" => in_account = true,
+                "ACCTID" if in_account => account = text.to_string(),
+                "CURDEF" => statement_currency = Some(text.to_string()),
+                "STMTTRN" => pending = Some(PendingTx { line, ..Default::default() }),
+                "CURRENCY" => in_currency = true,
+                _ => {
+                    if let Some(tx) = &mut pending {
+                        let value = Some(unescape(text));
+                        match name.as_str() {
+                            "DTPOSTED" => tx.date = value,
+                            "TRNAMT" => tx.amount = value,
+                            "FITID" => tx.fitid = value,
+                            "NAME" | "PAYEE" => tx.name = value,
+                            "MEMO" => tx.memo = value,
+                            "CURSYM" if in_currency => tx.currency = value,
+                            _ => {}
+                        }
+                    }
+                }
+            },
+            Token::Close(name) => match name.as_str() {
+                "BANKACCTFROM" | "CCACCTFROM" => in_account = false,
+                "CURRENCY" => in_currency = false,
+                "STMTTRN" => {
+                    if let Some(tx) = pending.take() {
+                        finish_transaction(tx, statement_currency.as_deref(), &account, &mut report);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+    report
+}
+
+fn finish_transaction(tx: PendingTx, statement_currency: Option<&str>, account: &str, report: &mut ImportReport) {
+    let line = tx.line;
+    let mut issue = |field: &str, reason: String| {
+        report.issues.push(ImportIssue { line, field: field.into(), reason });
+    };
+
+    // DTPOSTED is YYYYMMDD optionally followed by a time and timezone
+    let date = match tx.date.as_deref() {
+        None => {
+            issue("DTPOSTED", "missing".into());
+            None
+        }
+        Some(d) => {
+            let parsed = d.get(..8).and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok());
+            if parsed.is_none() {
+                issue("DTPOSTED", format!("'{d}' is not a YYYYMMDD date"));
+            }
+            parsed
+        }
+    };
+    let amount = match tx.amount.as_deref() {
+        None => {
+            issue("TRNAMT", "missing".into());
+            None
+        }
+        Some(a) => match a.replace(',', ".").parse::<f64>() {
+            Ok(amount) if amount.is_finite() => Some(-amount),
+            _ => {
+                issue("TRNAMT", format!("'{a}' is not a number"));
+                None
+            }
+        },
+    };
+    let currency = match tx.currency.as_deref().or(statement_currency) {
+        None => Some(Currency::default()),
+        Some(c) => {
+            let parsed = Currency::from_str(&c.to_uppercase()).ok();
+            if parsed.is_none() {
+                issue("CURDEF", format!("unsupported currency '{c}'"));
+            }
+            parsed
+        }
+    };
+
+    let description = match (tx.name, tx.memo) {
+        (Some(name), Some(memo)) if !memo.is_empty() && memo != name => format!("{name} - {memo}"),
+        (Some(name), _) => name,
+        (None, Some(memo)) => memo,
+        (None, None) => String::new(),
+    };
+
+    if let (Some(date), Some(amount), Some(currency)) = (date, amount, currency) {
+        report.rows.push(ImportRow::new(Transaction {
+            date,
+            description,
+            amount,
+            category: "Uncategorized".to_string(),
+            currency,
+            account: account.to_string(),
+            external_id: tx.fitid.filter(|id| !id.is_empty()),
+            ..Default::default()
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>EUR
+<BANKACCTFROM>
+<BANKID>10020030
+<ACCTID>12345678
+<ACCTTYPE>CHECKING
+</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260915120000[0:GMT]
+<TRNAMT>-12,50
+<FITID>A1
+<NAME>Caf&eacute; &amp; Bar
+<MEMO>Lunch
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20260916
+<TRNAMT>2000.00
+<FITID>A2
+<NAME>Salary
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260917
+<TRNAMT>-30.00
+<FITID>A3
+<NAME>Hotel New York
+<ORIGCURRENCY>
+<CURRATE>1.1
+<CURSYM>USD
+</ORIGCURRENCY>
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260918
+<TRNAMT>-8.00
+<FITID>A4
+<NAME>London Tube
+<CURRENCY>
+<CURRATE>0.85
+<CURSYM>GBP
+</CURRENCY>
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CURDEF>USD</CURDEF>
+    <CCACCTFROM><ACCTID>4111</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20261001</DTPOSTED>
+        <TRNAMT>-45.10</TRNAMT>
+        <FITID>X1</FITID>
+        <NAME>Books &lt;&amp;&gt; more</NAME>
+      </STMTTRN>
+      <STMTTRN>
+        <TRNTYPE>CREDIT</TRNTYPE>
+        <DTPOSTED>20261002</DTPOSTED>
+        <TRNAMT>5.00</TRNAMT>
+        <FITID>X2</FITID>
+        <MEMO>Refund</MEMO>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>
+"#;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parses_sgml_with_unclosed_leaf_elements() {
+        let report = parse_ofx(SGML);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.rows.len(), 4);
+        let lunch = &report.rows[0].transaction;
+        assert_eq!(lunch.date, date(2026, 9, 15));
+        assert_eq!(lunch.amount, 12.5);
+        assert_eq!(lunch.currency, Currency::EUR);
+        assert_eq!(lunch.account, "12345678");
+        assert_eq!(lunch.external_id.as_deref(), Some("A1"));
+        assert_eq!(lunch.description, "Caf&eacute; & Bar - Lunch");
+        let salary = &report.rows[1].transaction;
+        assert_eq!((salary.date, salary.amount), (date(2026, 9, 16), -2000.0));
+    }
+
+    #[test]
+    fn original_currency_keeps_the_statement_currency() {
+        let report = parse_ofx(SGML);
+        let hotel = &report.rows[2].transaction;
+        assert_eq!((hotel.amount, hotel.currency), (30.0, Currency::EUR));
+        let tube = &report.rows[3].transaction;
+        assert_eq!((tube.amount, tube.currency), (8.0, Currency::GBP));
+    }
+
+    #[test]
+    fn parses_xml() {
+        let report = parse_ofx(XML);
+        assert!(report.issues.is_empty());
+        assert_eq!(report.rows.len(), 2);
+        let books = &report.rows[0].transaction;
+        assert_eq!(books.description, "Books <&> more");
+        assert_eq!((books.amount, books.currency), (45.1, Currency::USD));
+        assert_eq!(books.account, "4111");
+        let refund = &report.rows[1].transaction;
+        assert_eq!((refund.description.as_str(), refund.amount), ("Refund", -5.0));
+    }
+
+    #[test]
+    fn reports_invalid_fields() {
+        let report = parse_ofx("<OFX><STMTTRN><DTPOSTED>2026-09-01<TRNAMT>abc</STMTTRN><STMTTRN><TRNAMT>1</STMTTRN></OFX>");
+        assert!(report.rows.is_empty());
+        let fields: Vec<&str> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, ["DTPOSTED", "TRNAMT", "DTPOSTED"]);
+    }
+
+    #[test]
+    fn reimported_fitids_are_duplicates() {
+        let mut state = AppState::load_or_default(None);
+        state.apply_import(&parse_ofx(SGML));
+        // A re-download with a changed description still matches on FITID
+        let mut report = parse_ofx(&SGML.replace("Salary", "SALARY PAYMENT"));
+        state.mark_duplicates(&mut report);
+        assert!(report.rows.iter().all(|r| r.action == DuplicateAction::Skip));
+        assert_eq!(report.rows.iter().map(|r| r.duplicate_of).collect::<Vec<_>>(), [Some(0), Some(1), Some(2), Some(3)]);
+        assert_eq!(state.apply_import(&report), 0);
+        assert_eq!(state.data.transactions.len(), 4);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::categories::is_within;
+use crate::types::*;
+use chrono::{Datelike, Days, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Weekly and bi-weekly periods are counted from this Monday.
+fn cycle_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+}
+
+/// Day `day` of the month `index` months after January of year 0, moved back to the last day
+/// of the month if the month is shorter.
+fn day_of_month(index: i32, day: u32) -> NaiveDate {
+    let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+    (1..=day.clamp(1, 31))
+        .rev()
+        .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+        .unwrap()
+}
+
+impl FromStr for BudgetPeriod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<BudgetPeriod, ()> {
+        match s.to_lowercase().replace(['-', '_'], "").as_str() {
+            "weekly" => Ok(BudgetPeriod::Weekly),
+            "biweekly" | "fortnightly" => Ok(BudgetPeriod::BiWeekly),
+            "monthly" => Ok(BudgetPeriod::Monthly),
+            "quarterly" => Ok(BudgetPeriod::Quarterly),
+            "yearly" | "annual" => Ok(BudgetPeriod::Yearly),
+            _ => Err(()),
+        }
+    }
+}
+
+impl BudgetPeriod {
+    pub fn all() -> &'static [BudgetPeriod] {
+        use BudgetPeriod::*;
+        &[Weekly, BiWeekly, Monthly, Quarterly, Yearly]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Weekly => "Weekly",
+            BudgetPeriod::BiWeekly => "Bi-weekly",
+            BudgetPeriod::Monthly => "Monthly",
+            BudgetPeriod::Quarterly => "Quarterly",
+            BudgetPeriod::Yearly => "Yearly",
+        }
+    }
+
+    /// Period length in days for weekly periods, or in months for the others.
+    fn length(&self) -> (u64, i32) {
+        match self {
+            BudgetPeriod::Weekly => (7, 0),
+            BudgetPeriod::BiWeekly => (14, 0),
+            BudgetPeriod::Monthly => (0, 1),
+            BudgetPeriod::Quarterly => (0, 3),
+            BudgetPeriod::Yearly => (0, 12),
+        }
+    }
+
+    /// The highest start day: the last day of the week, fortnight or month.
+    pub fn max_start_day(&self) -> u32 {
+        match self.length() {
+            (0, _) => 31,
+            (days, _) => days as u32,
+        }
+    }
+
+    /// Describes a start day, e.g. `Mon`, `Tue (week 2)` or `day 25`.
+    pub fn start_day_label(&self, start_day: u32) -> String {
+        let start = start_day.clamp(1, self.max_start_day());
+        match self.length() {
+            (0, _) => format!("day {start}"),
+            (7, _) => (cycle_epoch() + Days::new(start as u64 - 1)).format("%a").to_string(),
+            _ => format!("{} (week {})", (cycle_epoch() + Days::new(start as u64 - 1)).format("%a"), start.div_ceil(7)),
+        }
+    }
+
+    /// How many of these periods make up a month on average.
+    pub fn per_month(&self) -> f64 {
+        match self.length() {
+            (0, months) => 1.0 / months as f64,
+            (days, _) => 365.25 / 12.0 / days as f64,
+        }
+    }
+
+    /// The period containing `date`, as its first day and the first day of the next period.
+    /// Quarters start in January, April, July and October, and years in January.
+    pub fn containing(&self, start_day: u32, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let start_day = start_day.clamp(1, self.max_start_day());
+        match self.length() {
+            (0, months) => {
+                let index = date.year() * 12 + date.month0() as i32;
+                let mut first = index - index.rem_euclid(months);
+                if day_of_month(first, start_day) > date {
+                    first -= months;
+                }
+                (day_of_month(first, start_day), day_of_month(first + months, start_day))
+            }
+            (days, _) => {
+                let offset = (date - cycle_epoch()).num_days() - (start_day as i64 - 1);
+                let start = date - Days::new(offset.rem_euclid(days as i64) as u64);
+                (start, start + Days::new(days))
+            }
+        }
+    }
+}
+
+impl CategoryBudget {
+    /// The limit spread over an average month, for monthly reports.
+    pub fn monthly_amount(&self) -> f64 {
+        self.amount * self.period.per_month()
+    }
+
+    pub fn period_label(&self) -> String {
+        match (self.period, self.start_day) {
+            (BudgetPeriod::Monthly | BudgetPeriod::Quarterly | BudgetPeriod::Yearly, 1) => self.period.as_str().to_string(),
+            (period, day) => format!("{} from {}", period.as_str(), period.start_day_label(day)),
+        }
+    }
+}
+
+/// One period of a budget and what has been spent in it, in the base currency.
+#[derive(Clone)]
+pub struct BudgetProgress {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub budget: f64,
+    /// Carried over from the previous period; negative after overspending.
+    pub carried: f64,
+    pub spent: f64,
+}
+
+impl BudgetProgress {
+    /// The budget plus whatever was carried over.
+    pub fn allowance(&self) -> f64 {
+        self.budget + self.carried
+    }
+
+    pub fn remaining(&self) -> f64 {
+        self.allowance() - self.spent
+    }
+}
+
+impl AppState {
+    /// Category sums in the base currency for transactions dated from `start` up to, but not
+    /// including, `end`.
+    pub fn category_sums_between(&self, start: NaiveDate, end: NaiveDate) -> HashMap<String, f64> {
+        let mut sums = HashMap::new();
+        for tx in self.data.transactions.iter().filter(|t| t.date >= start && t.date < end) {
+            for (category, amount) in tx.category_amounts() {
+                let converted = self.convert(amount, tx.currency, self.base_currency);
+                *sums.entry(category.to_string()).or_insert(0.0) += converted;
+            }
+        }
+        sums
+    }
+
+    /// Spending in `category` and its subcategories per period of `limit`, keyed by the
+    /// period's first day, for periods before `end`.
+    pub fn spending_by_period(&self, category: &str, limit: &CategoryBudget, end: NaiveDate) -> BTreeMap<NaiveDate, f64> {
+        let mut spending = BTreeMap::new();
+        for tx in self.data.transactions.iter().filter(|t| t.date < end) {
+            for (_, amount) in tx.category_amounts().into_iter().filter(|(c, _)| is_within(c, category)) {
+                let start = limit.period.containing(limit.start_day, tx.date).0;
+                *spending.entry(start).or_insert(0.0) += self.convert(amount, tx.currency, self.base_currency);
+            }
+        }
+        spending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn containing_at_period_edges() {
+        use BudgetPeriod::*;
+        let cases = [
+            // Calendar months and a payday on the 25th
+            (Monthly, 1, date(2026, 12, 31), date(2026, 12, 1), date(2027, 1, 1)),
+            (Monthly, 1, date(2027, 1, 1), date(2027, 1, 1), date(2027, 2, 1)),
+            (Monthly, 25, date(2026, 3, 24), date(2026, 2, 25), date(2026, 3, 25)),
+            (Monthly, 25, date(2026, 3, 25), date(2026, 3, 25), date(2026, 4, 25)),
+            (Monthly, 25, date(2027, 1, 3), date(2026, 12, 25), date(2027, 1, 25)),
+            // The 31st falls back to the end of shorter months
+            (Monthly, 31, date(2026, 2, 27), date(2026, 1, 31), date(2026, 2, 28)),
+            (Monthly, 31, date(2026, 2, 28), date(2026, 2, 28), date(2026, 3, 31)),
+            (Monthly, 31, date(2026, 3, 30), date(2026, 2, 28), date(2026, 3, 31)),
+            (Monthly, 31, date(2028, 2, 29), date(2028, 2, 29), date(2028, 3, 31)),
+            (Quarterly, 1, date(2026, 3, 31), date(2026, 1, 1), date(2026, 4, 1)),
+            (Quarterly, 1, date(2026, 4, 1), date(2026, 4, 1), date(2026, 7, 1)),
+            (Quarterly, 15, date(2026, 1, 14), date(2025, 10, 15), date(2026, 1, 15)),
+            (Yearly, 1, date(2026, 12, 31), date(2026, 1, 1), date(2027, 1, 1)),
+            (Yearly, 15, date(2026, 1, 10), date(2025, 1, 15), date(2026, 1, 15)),
+            // Weeks start on Monday (1) or any later weekday
+            (Weekly, 1, date(2026, 10, 25), date(2026, 10, 19), date(2026, 10, 26)),
+            (Weekly, 1, date(2026, 10, 26), date(2026, 10, 26), date(2026, 11, 2)),
+            (Weekly, 5, date(2026, 10, 22), date(2026, 10, 16), date(2026, 10, 23)),
+            (Weekly, 40, date(2026, 10, 25), date(2026, 10, 25), date(2026, 11, 1)),
+            // Fortnights are counted from Monday 2024-01-01, in both directions
+            (BiWeekly, 1, date(2024, 1, 14), date(2024, 1, 1), date(2024, 1, 15)),
+            (BiWeekly, 1, date(2024, 1, 15), date(2024, 1, 15), date(2024, 1, 29)),
+            (BiWeekly, 1, date(2023, 12, 31), date(2023, 12, 18), date(2024, 1, 1)),
+            (BiWeekly, 8, date(2024, 1, 7), date(2023, 12, 25), date(2024, 1, 8)),
+            (BiWeekly, 8, date(2024, 1, 8), date(2024, 1, 8), date(2024, 1, 22)),
+        ];
+        for (period, start_day, day, start, end) in cases {
+            assert_eq!(period.containing(start_day, day), (start, end), "{} from {start_day} on {day}", period.as_str());
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::types::*;
+use chrono::{Datelike, NaiveDate};
+use std::str::FromStr;
+
+/// A search box query such as `cat:food amount>50 date:2026-09 coffee`.
+///
+/// Supported terms (all must match):
+/// - free text, or `"quoted text"`: description or category contains it
+/// - `cat:` / `category:` — category contains the value
+/// - `amount>50`, `amount>=50`, `amount<10`, `amount<=10`, `amount=12.5`, `amount:10..50` —
+///   compared against the amount in the base currency
+/// - `date:2026`, `date:2026-09`, `date:2026-09-15`, `date:2026-09-01..2026-09-30`,
+///   `date>2026-09-01`, `date<=2026-09-30`
+/// - `cur:` / `currency:` — e.g. `cur:EUR`
+/// - `recurring:yes` / `recurring:no`
+/// - `account:` / `acct:` — account contains the value
+/// - `tag:vacation` or `#vacation`
+#[derive(Default, Clone, Debug)]
+pub struct Query {
+    pub text: Vec<String>,
+    pub category: Option<String>,
+    pub min_amount: Option<(f64, bool)>,
+    pub max_amount: Option<(f64, bool)>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub currency: Option<Currency>,
+    pub recurring: Option<bool>,
+    pub account: Option<String>,
+    pub tags: Vec<String>,
+    /// Terms that could not be understood; they are ignored.
+    pub errors: Vec<String>,
+}
+
+/// Short syntax reference shown next to the search box and in the CLI usage.
+pub const HELP: &str = "Words match description or category. Filters: cat:food, amount>50, amount:10..50, \
+date:2026-09, date:2026-01-01..2026-03-31, date>=2026-09-01, cur:EUR, recurring:yes, account:checking, tag:trip or #trip";
+
+/// Splits on whitespace, keeping double-quoted phrases together.
+fn tokens(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// First and last day of a `YYYY`, `YYYY-MM` or `YYYY-MM-DD` period.
+fn date_span(value: &str) -> Option<(NaiveDate, NaiveDate)> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some((date, date));
+    }
+    if let Ok(first) = NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d") {
+        let next = if first.month() == 12 {
+            NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+        }?;
+        return Some((first, next.pred_opt()?));
+    }
+    let year: i32 = value.parse().ok().filter(|_| value.len() == 4)?;
+    Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year, 12, 31)?))
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Query {
+        let mut query = Query::default();
+        for token in tokens(input) {
+            if let Err(e) = query.apply(&token) {
+                query.errors.push(format!("{token}: {e}"));
+            }
+        }
+        query
+    }
+
+    fn apply(&mut self, token: &str) -> Result<(), String> {
+        if let Some(tag) = token.strip_prefix('#').filter(|t| !t.is_empty()) {
+            self.tags.push(tag.to_lowercase());
+            return Ok(());
+        }
+        // Comparison operators: amount>50, date<=2026-09-30
+        for op in [">=", "<=", ">", "<", "="] {
+            let Some((key, value)) = token.split_once(op) else { continue };
+            if key.contains(':') {
+                break;
+            }
+            let inclusive = op.len() == 2 || op == "=";
+            return match (key.to_lowercase().as_str(), op) {
+                ("amount" | "amt", _) => {
+                    let amount: f64 = value.parse().map_err(|_| "not a number".to_string())?;
+                    if op.starts_with('>') || op == "=" {
+                        self.min_amount = Some((amount, inclusive));
+                    }
+                    if op.starts_with('<') || op == "=" {
+                        self.max_amount = Some((amount, inclusive));
+                    }
+                    Ok(())
+                }
+                ("date", _) => {
+                    let (first, last) = date_span(value).ok_or("not a date")?;
+                    match op {
+                        ">" => self.from = last.succ_opt(),
+                        ">=" => self.from = Some(first),
+                        "<" => self.to = first.pred_opt(),
+                        "<=" => self.to = Some(last),
+                        _ => (self.from, self.to) = (Some(first), Some(last)),
+                    }
+                    Ok(())
+                }
+                _ => Err(format!("unknown field '{key}'")),
+            };
+        }
+        let Some((key, value)) = token.split_once(':') else {
+            self.text.push(token.to_lowercase());
+            return Ok(());
+        };
+        match key.to_lowercase().as_str() {
+            "cat" | "category" => self.category = Some(value.to_lowercase()),
+            "cur" | "currency" => {
+                self.currency = Some(Currency::from_str(&value.to_uppercase()).map_err(|_| "unsupported currency")?)
+            }
+            "recurring" => {
+                self.recurring = Some(match value.to_lowercase().as_str() {
+                    "yes" | "true" | "y" => true,
+                    "no" | "false" | "n" => false,
+                    _ => return Err("expected yes or no".into()),
+                })
+            }
+            "account" | "acct" => self.account = Some(value.to_lowercase()),
+            "tag" => self.tags.push(value.trim_start_matches('#').to_lowercase()),
+            "amount" | "amt" => {
+                let (min, max) = value.split_once("..").ok_or("expected min..max")?;
+                let parse = |s: &str| -> Result<Option<f64>, String> {
+                    if s.is_empty() { Ok(None) } else { s.parse().map(Some).map_err(|_| "not a number".to_string()) }
+                };
+                self.min_amount = parse(min)?.map(|a| (a, true));
+                self.max_amount = parse(max)?.map(|a| (a, true));
+            }
+            "date" => {
+                let (first, last) = match value.split_once("..") {
+                    Some((from, to)) => (
+                        if from.is_empty() { None } else { Some(date_span(from).ok_or("not a date")?.0) },
+                        if to.is_empty() { None } else { Some(date_span(to).ok_or("not a date")?.1) },
+                    ),
+                    None => {
+                        let (first, last) = date_span(value).ok_or("not a date")?;
+                        (Some(first), Some(last))
+                    }
+                };
+                self.from = first;
+                self.to = last;
+            }
+            _ => return Err(format!("unknown field '{key}'")),
+        }
+        Ok(())
+    }
+
+    /// Whether `tx` matches every term. `converted` is its amount in the base currency.
+    pub fn matches(&self, tx: &Transaction, converted: f64) -> bool {
+        let description = tx.description.to_lowercase();
+        let categories: Vec<String> = tx.category_amounts().iter().map(|(c, _)| c.to_lowercase()).collect();
+        let in_category = |t: &str| categories.iter().any(|c| c.contains(t));
+        self.text.iter().all(|t| description.contains(t) || in_category(t))
+            && self.category.as_ref().is_none_or(|c| in_category(c))
+            && self.min_amount.is_none_or(|(min, inclusive)| if inclusive { converted >= min } else { converted > min })
+            && self.max_amount.is_none_or(|(max, inclusive)| if inclusive { converted <= max } else { converted < max })
+            && self.from.is_none_or(|from| tx.date >= from)
+            && self.to.is_none_or(|to| tx.date <= to)
+            && self.currency.is_none_or(|c| tx.currency == c)
+            && self.recurring.is_none_or(|r| tx.recurring == r)
+            && self.account.as_ref().is_none_or(|a| tx.account.to_lowercase().contains(a))
+            && self.tags.iter().all(|t| tx.tags.iter().any(|tag| tag.to_lowercase() == *t))
+    }
+}
@@ -0,0 +1,342 @@
+use crate::categories::rollup;
+use crate::types::*;
+use chrono::{Datelike, NaiveDate};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point, Rect, Rgb,
+};
+use rust_xlsxwriter::{Color, Format, Workbook, Worksheet, XlsxError};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+
+/// Parses a `YYYY-MM` month.
+pub fn parse_month(month: &str) -> Option<(i32, u32)> {
+    let date = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d").ok()?;
+    Some((date.year(), date.month()))
+}
+
+fn header_row(sheet: &mut Worksheet, headers: &[String], format: &Format) -> Result<(), XlsxError> {
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, header, format)?;
+        sheet.set_column_width(col as u16, (header.len() as f64 + 4.0).max(12.0))?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(())
+}
+
+impl AppState {
+    /// Writes a spreadsheet report with three sheets: the exported transactions (with their
+    /// amount converted to the base currency), spending per month and category, and budget vs
+    /// actual for each month.
+    pub fn export_xlsx(&self, path: &PathBuf, options: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
+        let base = self.base_currency;
+        let header = Format::new().set_bold().set_background_color(Color::Theme(4, 1));
+        let money = Format::new().set_num_format("#,##0.00");
+        let over = Format::new().set_num_format("#,##0.00").set_font_color(Color::Red);
+        let percent = Format::new().set_num_format("0%");
+        let date = Format::new().set_num_format("yyyy-mm-dd");
+        let bold_money = Format::new().set_bold().set_num_format("#,##0.00");
+
+        let mut workbook = Workbook::new();
+
+        let sheet = workbook.add_worksheet().set_name("Transactions")?;
+        let headers = [
+            "Date".to_string(),
+            "Description".to_string(),
+            "Amount".to_string(),
+            "Currency".to_string(),
+            format!("Amount ({base})"),
+            "Category".to_string(),
+            "Account".to_string(),
+            "Recurring".to_string(),
+            "Tags".to_string(),
+        ];
+        header_row(sheet, &headers, &header)?;
+        sheet.set_column_width(1, 40)?;
+        let mut transactions = self.export_transactions(options);
+        transactions.sort_by_key(|t| t.date);
+        for (i, tx) in transactions.iter().enumerate() {
+            let row = i as u32 + 1;
+            sheet.write_datetime_with_format(row, 0, tx.date, &date)?;
+            sheet.write_string(row, 1, &tx.description)?;
+            sheet.write_number_with_format(row, 2, tx.amount, &money)?;
+            sheet.write_string(row, 3, tx.currency.as_str())?;
+            sheet.write_number_with_format(row, 4, self.convert(tx.amount, tx.currency, base), &money)?;
+            sheet.write_string(row, 5, tx.category_label())?;
+            sheet.write_string(row, 6, &tx.account)?;
+            sheet.write_string(row, 7, if tx.recurring { "Yes" } else { "No" })?;
+            sheet.write_string(row, 8, tx.tags.join(" "))?;
+        }
+        sheet.autofilter(0, 0, transactions.len() as u32, headers.len() as u16 - 1)?;
+
+        let months = self.category_sums_by_month(&transactions);
+        let mut categories: Vec<String> = transactions
+            .iter()
+            .flat_map(|t| t.category_amounts().into_iter().map(|(c, _)| c.to_string()))
+            .collect();
+        categories.sort();
+        categories.dedup();
+
+        let sheet = workbook.add_worksheet().set_name("Summary")?;
+        let mut headers = vec!["Month".to_string()];
+        headers.extend(categories.iter().cloned());
+        headers.push(format!("Total ({base})"));
+        header_row(sheet, &headers, &header)?;
+        for (i, ((year, month), sums)) in months.iter().enumerate() {
+            let row = i as u32 + 1;
+            sheet.write_string(row, 0, format!("{year}-{month:02}"))?;
+            for (col, category) in categories.iter().enumerate() {
+                let value = sums.get(category).copied().unwrap_or(0.0);
+                sheet.write_number_with_format(row, col as u16 + 1, value, &money)?;
+            }
+            let total: f64 = sums.values().sum();
+            sheet.write_number_with_format(row, categories.len() as u16 + 1, total, &bold_money)?;
+        }
+
+        let sheet = workbook.add_worksheet().set_name("Budget vs Actual")?;
+        let headers = [
+            "Month".to_string(),
+            "Category".to_string(),
+            format!("Budget ({base})"),
+            format!("Actual ({base})"),
+            format!("Remaining ({base})"),
+            "Used".to_string(),
+        ];
+        header_row(sheet, &headers, &header)?;
+        let mut categories: Vec<&String> = self.data.budget.limits.keys().collect();
+        categories.sort();
+        let mut row = 1;
+        for ((year, month), sums) in &months {
+            // A parent's budget covers its subcategories. Each month uses the limits in force
+            // when it began, and budgets for other periods are spread over an average month.
+            let sums = rollup(sums);
+            let first = NaiveDate::from_ymd_opt(*year, *month, 1).ok_or("invalid month")?;
+            for category in &categories {
+                let Some(limit) = self.data.budget.limit_on(category, first) else { continue };
+                let budget = self.convert(limit.monthly_amount(), limit.currency, base);
+                let actual = sums.get(*category).copied().unwrap_or(0.0);
+                let remaining = budget - actual;
+                sheet.write_string(row, 0, format!("{year}-{month:02}"))?;
+                sheet.write_string(row, 1, category.as_str())?;
+                sheet.write_number_with_format(row, 2, budget, &money)?;
+                sheet.write_number_with_format(row, 3, actual, &money)?;
+                sheet.write_number_with_format(row, 4, remaining, if remaining < 0.0 { &over } else { &money })?;
+                if budget > 0.0 {
+                    sheet.write_number_with_format(row, 5, actual / budget, &percent)?;
+                }
+                row += 1;
+            }
+        }
+
+        workbook.save(path)?;
+        Ok(())
+    }
+}
+
+/// Writes top to bottom across as many A4 pages as needed.
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    mono: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new(title: &str) -> Result<Self, printpdf::Error> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let mono = doc.add_builtin_font(BuiltinFont::Courier)?;
+        let layer = doc.get_page(page).get_layer(layer);
+        Ok(Self { doc, layer, regular, bold, mono, y: PAGE_HEIGHT - MARGIN })
+    }
+
+    /// Moves down by `height`, starting a new page when the bottom margin is reached.
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+        self.y -= height;
+    }
+
+    fn text(&self, x: f32, size: f32, font: &IndirectFontRef, text: &str) {
+        // The built-in fonts only cover Latin-1
+        let text: String = text.chars().map(|c| if (c as u32) < 256 { c } else { '?' }).collect();
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.advance(10.0);
+        self.text(MARGIN, 13.0, &self.bold, text);
+        self.advance(2.0);
+        self.rule();
+        self.advance(4.0);
+    }
+
+    fn rule(&self) {
+        self.layer.set_outline_color(printpdf::Color::Rgb(Rgb::new(0.6, 0.6, 0.6, None)));
+        self.layer.set_outline_thickness(0.5);
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(self.y)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(self.y)), false),
+            ],
+            is_closed: false,
+        });
+    }
+
+    fn bar(&self, x: f32, width: f32, height: f32, color: (f32, f32, f32)) {
+        self.layer.set_fill_color(printpdf::Color::Rgb(Rgb::new(color.0, color.1, color.2, None)));
+        self.layer.add_rect(Rect::new(Mm(x), Mm(self.y), Mm(x + width.max(0.3)), Mm(self.y + height)));
+        // Text is painted with the fill colour too
+        self.layer.set_fill_color(printpdf::Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    }
+
+    fn save(self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        self.doc.save(&mut BufWriter::new(File::create(path)?))?;
+        Ok(())
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() > max {
+        format!("{}...", text.chars().take(max - 3).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+impl AppState {
+    /// Renders a printable statement for one month: summary totals, a spending-by-category
+    /// chart with budget markers, budget status and the month's transactions.
+    pub fn export_monthly_pdf(&self, year: i32, month: u32, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let base = self.base_currency;
+        let month_name = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or("invalid month")?
+            .format("%B %Y")
+            .to_string();
+        let profile = self.file_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .and_then(|n| n.to_str())
+            .unwrap_or("Unsaved profile");
+        let mut transactions: Vec<&Transaction> = self.data.transactions
+            .iter()
+            .filter(|t| t.date.year() == year && t.date.month() == month)
+            .collect();
+        transactions.sort_by_key(|t| t.date);
+        let sums = self.category_sums_for_month(year, month);
+
+        let mut pdf = PdfWriter::new(&format!("Statement {month_name}"))?;
+        let (regular, bold, mono) = (pdf.regular.clone(), pdf.bold.clone(), pdf.mono.clone());
+        pdf.advance(4.0);
+        pdf.text(MARGIN, 20.0, &bold, &format!("Monthly Statement - {month_name}"));
+        pdf.advance(8.0);
+        pdf.text(MARGIN, 10.0, &regular, &format!("Profile: {profile}"));
+        pdf.text(120.0, 10.0, &regular, &format!("Base currency: {base}"));
+        pdf.advance(3.0);
+        pdf.rule();
+
+        let converted: Vec<f64> = transactions.iter().map(|t| self.convert(t.amount, t.currency, base)).collect();
+        let spending: f64 = converted.iter().filter(|a| **a > 0.0).sum();
+        let income: f64 = -converted.iter().filter(|a| **a < 0.0).sum::<f64>();
+        pdf.heading("Summary");
+        for (label, value) in [("Spending", spending), ("Income", income), ("Net", income - spending)] {
+            pdf.text(MARGIN, 10.0, &regular, label);
+            pdf.text(60.0, 10.0, &mono, &format!("{value:>12.2} {base}"));
+            pdf.advance(5.0);
+        }
+        pdf.text(MARGIN, 10.0, &regular, "Transactions");
+        pdf.text(60.0, 10.0, &mono, &format!("{:>12}", transactions.len()));
+
+        // Parents include their subcategories, so budgets set on a parent compare like for like
+        let sums = rollup(&sums);
+        let mut categories: Vec<(&String, f64)> = sums.iter().map(|(c, v)| (c, *v)).filter(|(_, v)| *v > 0.0).collect();
+        categories.sort_by(|a, b| b.1.total_cmp(&a.1));
+        // The limits in force when the month began
+        let first = NaiveDate::from_ymd_opt(year, month, 1).ok_or("invalid month")?;
+        let budget_for = |category: &str| {
+            self.data.budget
+                .limit_on(category, first)
+                .map(|b| self.convert(b.monthly_amount(), b.currency, base))
+        };
+        if !categories.is_empty() {
+            pdf.heading("Spending by Category");
+            let scale_max = categories
+                .iter()
+                .map(|(c, v)| v.max(budget_for(c).unwrap_or(0.0)))
+                .fold(0.0, f64::max);
+            let (chart_x, chart_width) = (60.0, 95.0);
+            for (category, value) in &categories {
+                pdf.advance(6.0);
+                pdf.text(MARGIN, 9.0, &regular, &truncate(category, 24));
+                let width = (*value / scale_max) as f32 * chart_width;
+                let over = budget_for(category).is_some_and(|b| *value > b);
+                let color = if over { (0.85, 0.3, 0.3) } else { (0.3, 0.5, 0.8) };
+                pdf.bar(chart_x, width, 3.5, color);
+                if let Some(budget) = budget_for(category) {
+                    let x = chart_x + (budget / scale_max) as f32 * chart_width;
+                    pdf.bar(x, 0.6, 5.0, (0.1, 0.1, 0.1));
+                }
+                pdf.text(chart_x + chart_width + 3.0, 9.0, &mono, &format!("{value:>10.2}"));
+            }
+            pdf.advance(6.0);
+            pdf.text(chart_x, 7.0, &regular, "Dark markers show the category budget.");
+        }
+
+        let mut limits: Vec<(&String, &CategoryBudget)> = self
+            .data
+            .budget
+            .limits
+            .keys()
+            .filter_map(|c| Some((c, self.data.budget.limit_on(c, first)?)))
+            .collect();
+        limits.sort_by(|a, b| a.0.cmp(b.0));
+        if !limits.is_empty() {
+            pdf.heading("Budget Status");
+            let columns = [MARGIN, 70.0, 105.0, 140.0, 175.0];
+            for (x, label) in columns.iter().zip(["Category", "Budget", "Spent", "Remaining", "Status"]) {
+                pdf.text(*x, 9.0, &bold, label);
+            }
+            for (category, limit) in limits {
+                pdf.advance(5.0);
+                let budget = self.convert(limit.monthly_amount(), limit.currency, base);
+                let spent = sums.get(category).copied().unwrap_or(0.0);
+                pdf.text(columns[0], 9.0, &regular, &truncate(category, 30));
+                pdf.text(columns[1], 9.0, &mono, &format!("{budget:>10.2}"));
+                pdf.text(columns[2], 9.0, &mono, &format!("{spent:>10.2}"));
+                pdf.text(columns[3], 9.0, &mono, &format!("{:>10.2}", budget - spent));
+                pdf.text(columns[4], 9.0, &regular, if spent > budget { "Over" } else { "OK" });
+            }
+        }
+
+        pdf.heading("Transactions");
+        let columns = [MARGIN, 38.0, 110.0, 145.0, 172.0];
+        let headers = ["Date", "Description", "Category", "Amount", &format!("Amount ({base})")];
+        for (x, label) in columns.iter().zip(headers) {
+            pdf.text(*x, 9.0, &bold, label);
+        }
+        for (tx, amount) in transactions.iter().zip(&converted) {
+            pdf.advance(5.0);
+            pdf.text(columns[0], 8.5, &regular, &tx.date.to_string());
+            pdf.text(columns[1], 8.5, &regular, &truncate(&tx.description, 42));
+            pdf.text(columns[2], 8.5, &regular, &truncate(&tx.category_label(), 20));
+            pdf.text(columns[3], 8.5, &mono, &format!("{:>9.2} {}", tx.amount, tx.currency));
+            pdf.text(columns[4], 8.5, &mono, &format!("{amount:>10.2}"));
+        }
+        if transactions.is_empty() {
+            pdf.advance(5.0);
+            pdf.text(MARGIN, 9.0, &regular, "No transactions this month.");
+        }
+
+        pdf.save(path)
+    }
+}
@@ -0,0 +1,181 @@
+use crate::periods::BudgetProgress;
+use crate::types::*;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+impl Rollover {
+    pub fn is_enabled(&self) -> bool {
+        self.unspent || self.overspent
+    }
+
+    /// What carries into the next period when `left` remains at the end of one (negative when
+    /// overspent). `cap` is in the base currency.
+    fn carry(&self, left: f64, cap: Option<f64>) -> f64 {
+        if left >= 0.0 {
+            if self.unspent { cap.map_or(left, |cap| left.min(cap)) } else { 0.0 }
+        } else if self.overspent {
+            left
+        } else {
+            0.0
+        }
+    }
+}
+
+impl AppState {
+    /// Every period of `category`'s budget up to the one containing `date`, oldest first, with
+    /// what was carried into each. Without rollover this is only the period containing `date`.
+    /// Each period is measured against the limit in force when it started.
+    pub fn budget_history(&self, category: &str, date: NaiveDate) -> Vec<BudgetProgress> {
+        let Some(limit) = self.data.budget.limits.get(category) else { return Vec::new() };
+        let (current, end) = limit.period.containing(limit.start_day, date);
+        let spending = self.spending_by_period(category, limit, end);
+        let budget_on = |start: NaiveDate| {
+            let version = self.data.budget.limit_on(category, start).unwrap_or(limit);
+            // Older limits for a different period are scaled to the current one
+            let amount = if version.period == limit.period {
+                version.amount
+            } else {
+                version.monthly_amount() / limit.period.per_month()
+            };
+            self.convert(amount, version.currency, self.base_currency)
+        };
+        let cap = limit.rollover.cap.map(|cap| self.convert(cap, limit.currency, self.base_currency));
+        let mut start = match (limit.rollover.is_enabled(), limit.rollover.since) {
+            (false, _) => current,
+            (true, Some(since)) => limit.period.containing(limit.start_day, since).0.min(current),
+            (true, None) => spending.keys().next().copied().unwrap_or(current),
+        };
+        let mut carried = 0.0;
+        let mut history = Vec::new();
+        loop {
+            let next = limit.period.containing(limit.start_day, start).1;
+            let spent = spending.get(&start).copied().unwrap_or(0.0);
+            let period = BudgetProgress { start, end: next, budget: budget_on(start), carried, spent };
+            carried = limit.rollover.carry(period.remaining(), cap);
+            history.push(period);
+            if start >= current {
+                return history;
+            }
+            start = next;
+        }
+    }
+
+    /// `budget_history`, kept until the transactions, budgets or rates change.
+    pub fn cached_budget_history(&mut self, category: &str, date: NaiveDate) -> Vec<BudgetProgress> {
+        let key = (self.data_version.get(), self.base_currency, date);
+        if self.budget_cache.as_ref().is_none_or(|(cached, _)| *cached != key) {
+            self.budget_cache = Some((key, HashMap::new()));
+        }
+        if let Some(history) = self.budget_cache.as_ref().and_then(|(_, cache)| cache.get(category)) {
+            return history.clone();
+        }
+        let history = self.budget_history(category, date);
+        if let Some((_, cache)) = &mut self.budget_cache {
+            cache.insert(category.to_string(), history.clone());
+        }
+        history
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// A monthly food budget of 100 USD with 60 spent in January, 150 in February and 20 so
+    /// far in March.
+    fn food_budget(rollover: Rollover) -> AppState {
+        let mut state = AppState::load_or_default(None);
+        for (day, category, amount) in [
+            (date(2026, 1, 10), "Food", 40.0),
+            (date(2026, 1, 12), "Food > Groceries", 20.0),
+            (date(2026, 2, 10), "Food", 150.0),
+            (date(2026, 3, 5), "Food", 20.0),
+            (date(2026, 3, 6), "Rent", 900.0),
+        ] {
+            state.data.transactions.push(Transaction {
+                date: day,
+                description: category.to_string(),
+                amount,
+                category: category.to_string(),
+                currency: Currency::USD,
+                ..Default::default()
+            });
+        }
+        let limit = CategoryBudget {
+            amount: 100.0,
+            currency: Currency::USD,
+            period: BudgetPeriod::Monthly,
+            start_day: 1,
+            rollover,
+            effective: None,
+        };
+        state.data.budget.set_limit("Food", limit, date(2026, 1, 1));
+        state
+    }
+
+    /// (period start, carried in, spent) of each period up to mid-March.
+    fn history(rollover: Rollover) -> Vec<(NaiveDate, f64, f64)> {
+        let state = food_budget(rollover);
+        state.budget_history("Food", date(2026, 3, 15)).iter().map(|p| (p.start, p.carried, p.spent)).collect()
+    }
+
+    #[test]
+    fn without_rollover_only_the_current_period_counts() {
+        assert_eq!(history(Rollover::default()), [(date(2026, 3, 1), 0.0, 20.0)]);
+    }
+
+    #[test]
+    fn each_rollover_mode_carries_its_side() {
+        let unspent = Rollover { unspent: true, ..Default::default() };
+        assert_eq!(history(unspent), [
+            (date(2026, 1, 1), 0.0, 60.0),
+            (date(2026, 2, 1), 40.0, 150.0),
+            (date(2026, 3, 1), 0.0, 20.0),
+        ]);
+        let overspent = Rollover { overspent: true, ..Default::default() };
+        assert_eq!(history(overspent), [
+            (date(2026, 1, 1), 0.0, 60.0),
+            (date(2026, 2, 1), 0.0, 150.0),
+            (date(2026, 3, 1), -50.0, 20.0),
+        ]);
+        let both = Rollover { unspent: true, overspent: true, ..Default::default() };
+        assert_eq!(history(both), [
+            (date(2026, 1, 1), 0.0, 60.0),
+            (date(2026, 2, 1), 40.0, 150.0),
+            (date(2026, 3, 1), -10.0, 20.0),
+        ]);
+    }
+
+    #[test]
+    fn cap_limits_unspent_budget_but_not_overspending() {
+        let capped = Rollover { unspent: true, overspent: true, cap: Some(25.0), since: None };
+        assert_eq!(history(capped), [
+            (date(2026, 1, 1), 0.0, 60.0),
+            (date(2026, 2, 1), 25.0, 150.0),
+            (date(2026, 3, 1), -25.0, 20.0),
+        ]);
+    }
+
+    #[test]
+    fn since_starts_carrying_from_its_period() {
+        let since = Rollover { unspent: true, overspent: true, cap: None, since: Some(date(2026, 2, 10)) };
+        assert_eq!(history(since), [(date(2026, 2, 1), 0.0, 150.0), (date(2026, 3, 1), -50.0, 20.0)]);
+        let progress = food_budget(Rollover { since: Some(date(2026, 2, 10)), ..Default::default() })
+            .budget_history("Food", date(2026, 3, 15));
+        assert_eq!(progress.len(), 1, "since has no effect without rollover");
+    }
+
+    #[test]
+    fn budget_history_is_cached_until_saved() {
+        let mut state = food_budget(Rollover::default());
+        assert_eq!(state.cached_budget_history("Food", date(2026, 3, 15))[0].spent, 20.0);
+        state.data.transactions[3].amount = 35.0;
+        assert_eq!(state.cached_budget_history("Food", date(2026, 3, 15))[0].spent, 20.0);
+        state.save();
+        assert_eq!(state.cached_budget_history("Food", date(2026, 3, 15))[0].spent, 35.0);
+    }
+}
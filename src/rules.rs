@@ -0,0 +1,135 @@
+use crate::categories::normalize_category;
+use crate::tags::has_tag;
+use crate::types::*;
+use regex::{Regex, RegexBuilder};
+
+/// A rule with its pattern compiled once, for applying it to many transactions.
+struct CompiledRule<'a> {
+    rule: &'a CategoryRule,
+    regex: Option<Regex>,
+    needle: String,
+}
+
+impl CategoryRule {
+    /// Checks that a regex pattern compiles and that the rule assigns something.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.match_kind == MatchKind::Regex {
+            RegexBuilder::new(&self.pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("invalid pattern: {e}"))?;
+        }
+        if self.category.trim().is_empty() && self.tags.is_empty() {
+            return Err("a rule needs a category or tags".to_string());
+        }
+        Ok(())
+    }
+
+    /// One-line description for the rules list.
+    pub fn summary(&self) -> String {
+        let mut conditions = Vec::new();
+        if !self.pattern.is_empty() {
+            match self.match_kind {
+                MatchKind::Contains => conditions.push(format!("description contains \"{}\"", self.pattern)),
+                MatchKind::Regex => conditions.push(format!("description matches /{}/", self.pattern)),
+            }
+        }
+        match (self.min_amount, self.max_amount) {
+            (Some(min), Some(max)) => conditions.push(format!("amount {min}..{max}")),
+            (Some(min), None) => conditions.push(format!("amount >= {min}")),
+            (None, Some(max)) => conditions.push(format!("amount <= {max}")),
+            (None, None) => {}
+        }
+        if let Some(currency) = self.currency {
+            conditions.push(format!("currency {currency}"));
+        }
+        if !self.account.is_empty() {
+            conditions.push(format!("account contains \"{}\"", self.account));
+        }
+        let when = if conditions.is_empty() { "always".to_string() } else { conditions.join(" and ") };
+        let mut then = Vec::new();
+        if !self.category.is_empty() {
+            then.push(format!("category {}", self.category));
+        }
+        if !self.tags.is_empty() {
+            then.push(format!("tags {}", self.tags.join(", ")));
+        }
+        format!("If {when} → {}", then.join(", "))
+    }
+}
+
+impl<'a> CompiledRule<'a> {
+    /// Rules whose regex doesn't compile are skipped.
+    fn compile(rule: &'a CategoryRule) -> Option<Self> {
+        let regex = match rule.match_kind {
+            MatchKind::Regex => Some(RegexBuilder::new(&rule.pattern).case_insensitive(true).build().ok()?),
+            MatchKind::Contains => None,
+        };
+        Some(CompiledRule { rule, regex, needle: rule.pattern.to_lowercase() })
+    }
+
+    fn matches(&self, tx: &Transaction) -> bool {
+        let rule = self.rule;
+        let description_matches = match &self.regex {
+            Some(regex) => regex.is_match(&tx.description),
+            None => tx.description.to_lowercase().contains(&self.needle),
+        };
+        description_matches
+            && rule.min_amount.is_none_or(|min| tx.amount >= min)
+            && rule.max_amount.is_none_or(|max| tx.amount <= max)
+            && rule.currency.is_none_or(|c| tx.currency == c)
+            && tx.account.to_lowercase().contains(&rule.account.to_lowercase())
+    }
+
+    /// Sets the rule's category (unless the transaction is split) and adds its tags. Returns
+    /// whether anything changed.
+    fn apply(&self, tx: &mut Transaction) -> bool {
+        let mut changed = false;
+        let category = normalize_category(&self.rule.category);
+        if !category.is_empty() && !tx.is_split() && tx.category != category {
+            tx.category = category;
+            changed = true;
+        }
+        for tag in &self.rule.tags {
+            if !has_tag(tx, tag) {
+                tx.tags.push(tag.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+impl AppState {
+    fn compiled_rules(&self) -> Vec<CompiledRule<'_>> {
+        self.data.rules.iter().filter_map(CompiledRule::compile).collect()
+    }
+
+    /// Applies the first matching rule to `tx`. Returns whether it changed.
+    pub fn apply_rules(&self, tx: &mut Transaction) -> bool {
+        self.compiled_rules().iter().find(|r| r.matches(tx)).is_some_and(|r| r.apply(tx))
+    }
+
+    /// Categorises imported rows before they are reviewed.
+    pub fn apply_rules_to_report(&self, report: &mut ImportReport) {
+        let rules = self.compiled_rules();
+        for row in &mut report.rows {
+            if let Some(rule) = rules.iter().find(|r| r.matches(&row.transaction)) {
+                rule.apply(&mut row.transaction);
+            }
+        }
+    }
+
+    /// Re-runs the rules over every transaction. Returns how many were changed.
+    pub fn reapply_rules(&mut self) -> usize {
+        let rules: Vec<CategoryRule> = self.data.rules.clone();
+        let compiled: Vec<CompiledRule> = rules.iter().filter_map(CompiledRule::compile).collect();
+        let mut changed = 0;
+        for tx in &mut self.data.transactions {
+            if compiled.iter().find(|r| r.matches(tx)).is_some_and(|r| r.apply(tx)) {
+                changed += 1;
+            }
+        }
+        changed
+    }
+}
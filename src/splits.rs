@@ -0,0 +1,67 @@
+use crate::types::*;
+
+/// Split lines may be off from the total by rounding.
+const SPLIT_TOLERANCE: f64 = 0.005;
+
+impl Transaction {
+    pub fn is_split(&self) -> bool {
+        !self.splits.is_empty()
+    }
+
+    /// Category and amount of each split line, or of the whole transaction if it isn't split.
+    pub fn category_amounts(&self) -> Vec<(&str, f64)> {
+        if self.splits.is_empty() {
+            vec![(self.category.as_str(), self.amount)]
+        } else {
+            self.splits.iter().map(|s| (s.category.as_str(), s.amount)).collect()
+        }
+    }
+
+    /// Whether the transaction or one of its split lines is in a category satisfying `f`.
+    pub fn any_category(&self, f: impl Fn(&str) -> bool) -> bool {
+        self.category_amounts().iter().any(|(category, _)| f(category))
+    }
+
+    /// The category to show in lists: the category, or `Split: Food, Household`.
+    pub fn category_label(&self) -> String {
+        if self.splits.is_empty() {
+            self.category.clone()
+        } else {
+            let categories: Vec<&str> = self.splits.iter().map(|s| s.category.as_str()).collect();
+            format!("Split: {}", categories.join(", "))
+        }
+    }
+}
+
+/// Checks that split lines have categories and add up to `total`.
+pub fn validate_splits(total: f64, splits: &[Split]) -> Result<(), String> {
+    if splits.len() < 2 {
+        return Err("a split needs at least two lines".to_string());
+    }
+    if splits.iter().any(|s| s.category.trim().is_empty()) {
+        return Err("every split line needs a category".to_string());
+    }
+    let sum: f64 = splits.iter().map(|s| s.amount).sum();
+    if (sum - total).abs() > SPLIT_TOLERANCE {
+        return Err(format!("split lines add up to {sum:.2}, not {total:.2}"));
+    }
+    Ok(())
+}
+
+impl AppState {
+    /// Replaces the split lines of transaction `i`; an empty list un-splits it. The
+    /// transaction's own category becomes that of its largest line.
+    pub fn set_splits(&mut self, i: usize, splits: Vec<Split>) -> Result<(), String> {
+        let tx = self.data.transactions.get_mut(i).ok_or("no such transaction")?;
+        if splits.is_empty() {
+            tx.splits.clear();
+            return Ok(());
+        }
+        validate_splits(tx.amount, &splits)?;
+        if let Some(largest) = splits.iter().max_by(|a, b| a.amount.abs().total_cmp(&b.amount.abs())) {
+            tx.category = largest.category.clone();
+        }
+        tx.splits = splits;
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::types::*;
+use std::collections::HashMap;
+
+/// Lowercase words of a description, ignoring numbers and one-letter fragments.
+fn tokens(description: &str) -> Vec<String> {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() > 1 && !t.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// A multinomial naive-Bayes classifier from description words to categories, trained on
+/// past transactions.
+#[derive(Default)]
+pub struct CategoryModel {
+    documents: HashMap<String, usize>,
+    token_counts: HashMap<String, HashMap<String, usize>>,
+    token_totals: HashMap<String, usize>,
+    vocabulary: usize,
+    total_documents: usize,
+}
+
+impl CategoryModel {
+    pub fn train(transactions: &[Transaction]) -> Self {
+        let mut model = CategoryModel::default();
+        let mut vocabulary = std::collections::HashSet::new();
+        for tx in transactions {
+            if tx.category.is_empty() || tx.category == "Uncategorized" {
+                continue;
+            }
+            *model.documents.entry(tx.category.clone()).or_default() += 1;
+            model.total_documents += 1;
+            let counts = model.token_counts.entry(tx.category.clone()).or_default();
+            for token in tokens(&tx.description) {
+                *counts.entry(token.clone()).or_default() += 1;
+                *model.token_totals.entry(tx.category.clone()).or_default() += 1;
+                vocabulary.insert(token);
+            }
+        }
+        model.vocabulary = vocabulary.len();
+        model
+    }
+
+    /// Categories ranked by probability for `description`, most likely first. Empty when the
+    /// description has no known words.
+    pub fn predict(&self, description: &str) -> Vec<(String, f64)> {
+        let words: Vec<String> = tokens(description)
+            .into_iter()
+            .filter(|t| self.token_counts.values().any(|c| c.contains_key(t)))
+            .collect();
+        if words.is_empty() || self.total_documents == 0 {
+            return Vec::new();
+        }
+        // Log-probabilities with add-one smoothing
+        let scores: Vec<(String, f64)> = self
+            .documents
+            .iter()
+            .map(|(category, &docs)| {
+                let counts = &self.token_counts[category];
+                let total = self.token_totals.get(category).copied().unwrap_or(0);
+                let denominator = (total + self.vocabulary) as f64;
+                let prior = (docs as f64 / self.total_documents as f64).ln();
+                let likelihood: f64 = words
+                    .iter()
+                    .map(|w| ((counts.get(w).copied().unwrap_or(0) + 1) as f64 / denominator).ln())
+                    .sum();
+                (category.clone(), prior + likelihood)
+            })
+            .collect();
+        let max = scores.iter().map(|s| s.1).fold(f64::NEG_INFINITY, f64::max);
+        let sum: f64 = scores.iter().map(|s| (s.1 - max).exp()).sum();
+        let mut ranked: Vec<(String, f64)> = scores.into_iter().map(|(c, s)| (c, (s - max).exp() / sum)).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+impl AppState {
+    /// Up to three likely categories for the description being entered.
+    pub fn suggest_categories(&self, description: &str) -> Vec<(String, f64)> {
+        let mut ranked = CategoryModel::train(&self.data.transactions).predict(description);
+        ranked.truncate(3);
+        ranked
+    }
+
+    /// Past descriptions containing `text`, most frequent first.
+    pub fn description_completions(&self, text: &str, limit: usize) -> Vec<String> {
+        let needle = text.trim().to_lowercase();
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for tx in &self.data.transactions {
+            if tx.description != text && tx.description.to_lowercase().contains(&needle) {
+                *counts.entry(tx.description.as_str()).or_default() += 1;
+            }
+        }
+        let mut matches: Vec<(&str, usize)> = counts.into_iter().collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        matches.into_iter().take(limit).map(|(d, _)| d.to_string()).collect()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::types::*;
+use std::collections::BTreeMap;
+
+/// Tags are stored without the leading `#` and can't contain spaces.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+/// Splits `#vacation2026, reimbursable` or `vacation2026 reimbursable` into tags.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split([',', ' ', ';']).map(normalize_tag).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+pub fn has_tag(tx: &Transaction, tag: &str) -> bool {
+    tx.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+}
+
+impl AppState {
+    /// Every tag in use, sorted.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.data.transactions.iter().flat_map(|t| t.tags.iter().cloned()).collect();
+        tags.sort_by_key(|t| t.to_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        tags
+    }
+
+    /// Transaction count and total in the base currency per tag. A transaction with several
+    /// tags counts towards each of them.
+    pub fn tag_totals(&self, transactions: &[&Transaction]) -> BTreeMap<String, (usize, f64)> {
+        let mut totals: BTreeMap<String, (usize, f64)> = BTreeMap::new();
+        for tx in transactions {
+            let converted = self.convert(tx.amount, tx.currency, self.base_currency);
+            for tag in &tx.tags {
+                let entry = totals.entry(tag.to_lowercase()).or_default();
+                entry.0 += 1;
+                entry.1 += converted;
+            }
+        }
+        totals
+    }
+
+    pub fn add_tag(&mut self, i: usize, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        match self.data.transactions.get_mut(i) {
+            Some(tx) if !tag.is_empty() && !has_tag(tx, &tag) => {
+                tx.tags.push(tag);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn remove_tag(&mut self, i: usize, tag: &str) -> bool {
+        match self.data.transactions.get_mut(i) {
+            Some(tx) => {
+                let before = tx.tags.len();
+                tx.tags.retain(|t| !t.eq_ignore_ascii_case(tag));
+                tx.tags.len() != before
+            }
+            None => false,
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use crate::periods::BudgetProgress;
 use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
 use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -54,25 +57,110 @@ impl FromStr for Currency {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
 pub struct Transaction {
     pub date: NaiveDate,
     pub description: String,
+    /// Positive for spending, negative for income and refunds.
     pub amount: f64,
     pub category: String,
     pub recurring: bool,
     pub currency: Currency,
+    /// Account the transaction belongs to, e.g. the account number from a bank statement.
+    #[serde(default)]
+    pub account: String,
+    /// Bank-assigned transaction id (OFX FITID), used to recognise re-imported transactions.
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// Free-form labels, stored without the leading `#`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// When not empty, analytics and budgets count these lines instead of `category`. They
+    /// add up to `amount`.
+    #[serde(default)]
+    pub splits: Vec<Split>,
+}
+
+/// One category/amount line of a split transaction.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct Split {
+    pub category: String,
+    pub amount: f64,
+    #[serde(default)]
+    pub memo: String,
+}
+
+/// How often a budget's limit starts over.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum BudgetPeriod {
+    Weekly,
+    BiWeekly,
+    #[default]
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+fn first_day() -> u32 {
+    1
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CategoryBudget {
     pub amount: f64,
     pub currency: Currency,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+    /// First day of each period: the day of the month (e.g. payday on the 25th) for monthly and
+    /// longer periods, the day of the week or fortnight (1 = Monday) for shorter ones.
+    #[serde(default = "first_day")]
+    pub start_day: u32,
+    #[serde(default)]
+    pub rollover: Rollover,
+    /// First day this limit applies to; earlier periods use the limit it replaced. `None`
+    /// applies to every period before the next change.
+    #[serde(default)]
+    pub effective: Option<NaiveDate>,
+}
+
+/// What a budget carries from one period into the next.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Rollover {
+    /// Unspent budget is added to the next period.
+    pub unspent: bool,
+    /// Overspending is taken off the next period.
+    pub overspent: bool,
+    /// Most unspent budget that can build up, in the budget's currency.
+    pub cap: Option<f64>,
+    /// First period to carry from; the first period with spending if not set.
+    pub since: Option<NaiveDate>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct Budget {
-    pub monthly_limits: HashMap<String, CategoryBudget>,
+    /// The latest limit of each category.
+    #[serde(alias = "monthly_limits")]
+    pub limits: HashMap<String, CategoryBudget>,
+    /// Limits replaced by later ones, oldest first.
+    #[serde(default)]
+    pub history: HashMap<String, Vec<CategoryBudget>>,
+    /// Zero-based budgeting: income is assigned into category envelopes.
+    #[serde(default)]
+    pub envelope_mode: bool,
+    /// Money assigned into (or, when negative, taken out of) envelopes, in order.
+    #[serde(default)]
+    pub assignments: Vec<Assignment>,
+}
+
+/// Money put into a category's envelope.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Assignment {
+    pub date: NaiveDate,
+    pub category: String,
+    pub amount: f64,
+    pub currency: Currency,
+    #[serde(default)]
+    pub memo: String,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -80,8 +168,236 @@ pub struct BudgetAppData {
     pub transactions: Vec<Transaction>,
     pub budget: Budget,
     pub last_profile: Option<String>,
+    /// Categories kept even when no transaction uses them, as `Parent > Child` paths.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub category_styles: HashMap<String, CategoryStyle>,
+    /// Auto-categorisation rules, tried in order.
+    #[serde(default)]
+    pub rules: Vec<CategoryRule>,
+    #[serde(default)]
+    pub goals: Vec<SavingsGoal>,
+    #[serde(default)]
+    pub loans: Vec<Loan>,
+    /// Current balance of each account in the base currency, where the forecast starts from.
+    #[serde(default)]
+    pub account_balances: HashMap<String, f64>,
+}
+
+/// A loan repaid in equal monthly installments. Payments are transactions in `category`, split
+/// into its `Principal` and `Interest` subcategories.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Loan {
+    pub name: String,
+    pub principal: f64,
+    /// Yearly interest rate in percent.
+    pub rate: f64,
+    pub term_months: u32,
+    pub currency: Currency,
+    /// Date of the first installment.
+    pub start: NaiveDate,
+    pub category: String,
+}
+
+/// Something being saved towards. Transactions matching every link that is set count as
+/// savings.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavingsGoal {
+    pub name: String,
+    pub target: f64,
+    pub currency: Currency,
+    pub target_date: Option<NaiveDate>,
+    /// Spending in this category (and its subcategories) is money put aside.
+    #[serde(default)]
+    pub category: String,
+    /// A savings account: money coming into it is put aside.
+    #[serde(default)]
+    pub account: String,
+    #[serde(default)]
+    pub tag: String,
+    /// Saved before tracking started, in the goal's currency.
+    #[serde(default)]
+    pub initial: f64,
 }
 
+/// How a rule's pattern is compared with the description.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum MatchKind {
+    #[default]
+    Contains,
+    Regex,
+}
+
+/// Assigns a category and tags to transactions that meet all of its conditions. Empty or
+/// missing conditions match anything.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CategoryRule {
+    pub pattern: String,
+    pub match_kind: MatchKind,
+    /// Inclusive bounds on the amount in the transaction's own currency.
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    pub currency: Option<Currency>,
+    pub account: String,
+    pub category: String,
+    pub tags: Vec<String>,
+}
+
+/// How a category is shown in the table and charts; subcategories inherit their parent's.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CategoryStyle {
+    pub color: Option<[u8; 3]>,
+    pub icon: String,
+}
+
+/// A problem found while validating one row of an imported file.
+#[derive(Clone)]
+pub struct ImportIssue {
+    pub line: usize,
+    pub field: String,
+    pub reason: String,
+}
+
+/// File formats that can be imported into a profile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    Ofx,
+    Qif,
+    Camt053,
+    Mt940,
+    Ledger,
+}
+
+impl ImportFormat {
+    pub fn all() -> &'static [ImportFormat] {
+        &[ImportFormat::Csv, ImportFormat::Ofx, ImportFormat::Qif, ImportFormat::Camt053, ImportFormat::Mt940, ImportFormat::Ledger]
+    }
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Ofx => "OFX/QFX",
+            ImportFormat::Qif => "QIF",
+            ImportFormat::Camt053 => "CAMT.053",
+            ImportFormat::Mt940 => "MT940",
+            ImportFormat::Ledger => "Ledger journal",
+        }
+    }
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ImportFormat::Csv => &["csv"],
+            ImportFormat::Ofx => &["ofx", "qfx"],
+            ImportFormat::Qif => &["qif"],
+            ImportFormat::Camt053 => &["xml"],
+            ImportFormat::Mt940 => &["sta", "mt940"],
+            ImportFormat::Ledger => &["journal", "ledger", "hledger"],
+        }
+    }
+    /// Looks a format up by label or extension, ignoring case (e.g. `qif`, `camt.053`).
+    pub fn from_name(name: &str) -> Option<ImportFormat> {
+        let name = name.to_lowercase();
+        Self::all().iter().copied().find(|f| {
+            f.label().to_lowercase() == name || f.extensions().contains(&name.as_str())
+        })
+    }
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &std::path::Path) -> Option<ImportFormat> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        Self::all().iter().copied().find(|f| f.extensions().contains(&ext.as_str()))
+    }
+}
+
+/// What to do with an imported row that looks like an existing transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateAction {
+    Keep,
+    Skip,
+    Merge,
+}
+
+/// A validated row waiting to be imported.
+pub struct ImportRow {
+    pub transaction: Transaction,
+    /// Index into `data.transactions` of the suspected duplicate, if any. Use `duplicate_in`,
+    /// as the index goes stale if transactions are deleted while the import is reviewed.
+    pub duplicate_of: Option<usize>,
+    /// Date, amount and description of the suspected duplicate, to recognise it again.
+    pub duplicate_key: Option<(NaiveDate, f64, String)>,
+    pub action: DuplicateAction,
+}
+
+impl ImportRow {
+    pub fn new(transaction: Transaction) -> Self {
+        Self { transaction, duplicate_of: None, duplicate_key: None, action: DuplicateAction::Keep }
+    }
+}
+
+/// Result of parsing an import file: the rows that validated plus a report of the ones that didn't.
+#[derive(Default)]
+pub struct ImportReport {
+    pub rows: Vec<ImportRow>,
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub fn duplicates(&self) -> usize {
+        self.rows.iter().filter(|r| r.duplicate_of.is_some()).count()
+    }
+
+    /// Whether the user has to decide something before the rows are imported.
+    pub fn needs_review(&self) -> bool {
+        !self.issues.is_empty() || self.duplicates() > 0
+    }
+
+    /// Number of distinct rows with at least one issue.
+    pub fn flagged_rows(&self) -> usize {
+        let mut lines: Vec<usize> = self.issues.iter().map(|i| i.line).collect();
+        lines.sort();
+        lines.dedup();
+        lines.len()
+    }
+}
+
+/// How transactions are selected and laid out by the exporters.
+#[derive(Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Only export the transactions matching the active search and filters.
+    pub filtered: bool,
+    /// Add the amount converted to the base currency.
+    pub include_converted: bool,
+    /// Only export the selected transactions; takes precedence over `filtered`.
+    pub selection: bool,
+}
+
+/// Column the transaction table is sorted by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortColumn {
+    Date,
+    Description,
+    Amount,
+    Converted,
+    Category,
+}
+
+/// The filter and sort settings the cached table rows were built for.
+#[derive(Clone, PartialEq)]
+pub struct RowsKey {
+    pub data_version: u64,
+    pub search_term: String,
+    pub filter_from: String,
+    pub filter_to: String,
+    pub filter_category: Option<String>,
+    pub filter_currency: Option<Currency>,
+    pub filter_tag: Option<String>,
+    pub sort_column: Option<SortColumn>,
+    pub sort_ascending: bool,
+    pub base_currency: Currency,
+}
+
+/// Data version, base currency and date the cached budget histories were computed for.
+pub type BudgetCacheKey = (u64, Currency, NaiveDate);
+
 #[derive(PartialEq)]
 pub enum Theme {
     Light,
@@ -96,14 +412,77 @@ pub struct AppState {
     pub input_date_str: String, // changed from NaiveDate to user-editable string
     pub input_recurring: bool,
     pub input_currency: Currency,
+    pub input_tags: String, // comma or space separated
     pub search_term: String,
+    pub filter_from: String, // YYYY-MM-DD, empty for no lower bound
+    pub filter_to: String,   // YYYY-MM-DD, empty for no upper bound
+    pub filter_category: Option<String>,
+    pub filter_currency: Option<Currency>,
+    pub filter_tag: Option<String>,
+    pub export_options: ExportOptions,
+    pub sort_column: Option<SortColumn>, // None keeps insertion order
+    pub sort_ascending: bool,
     pub file_path: Option<std::path::PathBuf>,
-    pub selected_tx: Option<usize>,
+    pub selected_tx: Option<usize>, // last clicked row, loaded into the edit form
+    pub selection: BTreeSet<usize>,
+    pub bulk_category: String,
+    pub bulk_currency: Currency,
+    pub bulk_tag: String,
+    pub tag_input: String, // tag being added to a single transaction
+    pub split_tx: Option<usize>, // transaction whose split is being edited
+    pub split_lines: Vec<(String, String, String)>, // category, amount, memo
+    pub show_category_manager: bool,
+    pub category_editing: Option<String>, // category selected in the manager
+    pub category_name_input: String,
+    pub category_target: Option<String>, // merge / reassignment target
+    pub new_category_input: String,
+    pub suggestions_for: String, // description the cached suggestions were computed for
+    pub category_suggestions: Vec<(String, f64)>,
+    pub description_completions: Vec<String>,
+    pub show_rules: bool,
+    pub show_budget_history: bool,
+    pub envelope_category: String,
+    pub envelope_amount: String,
+    pub envelope_move_to: Option<String>,
+    pub rule_pattern: String,
+    pub rule_regex: bool,
+    pub rule_min: String,
+    pub rule_max: String,
+    pub rule_currency: Option<Currency>,
+    pub rule_account: String,
+    pub rule_category: String,
+    pub rule_tags: String, // comma-separated
+    pub show_goals: bool,
+    pub goal_editing: Option<usize>, // goal loaded into the form
+    pub goal_name: String,
+    pub goal_target: String,
+    pub goal_currency: Currency,
+    pub goal_date: String, // YYYY-MM-DD, optional
+    pub goal_category: String,
+    pub goal_account: String,
+    pub goal_tag: String,
+    pub goal_initial: String,
+    pub show_loans: bool,
+    pub loan_schedule: Option<usize>, // loan whose schedule is shown
+    pub loan_name: String,
+    pub loan_principal: String,
+    pub loan_rate: String,
+    pub loan_term: String,
+    pub loan_currency: Currency,
+    pub loan_start: String,
+    pub loan_category: String,
+    pub show_forecast: bool,
+    pub forecast_months: u32,
     pub theme: Theme,
     pub show_import_modal: bool,
     pub import_path: Option<std::path::PathBuf>,
+    pub pending_import: Option<ImportReport>,
+    pub report_month: String, // YYYY-MM, for the monthly PDF statement
     pub base_currency: Currency,
     pub exchange_rates: HashMap<(Currency, Currency), f64>,
     pub editing_rates: bool,
     pub rates_api_error: Option<String>,
+    pub data_version: Cell<u64>, // bumped on every save, so cached views are rebuilt
+    pub rows_cache: Option<(RowsKey, Rc<[usize]>)>,
+    pub budget_cache: Option<(BudgetCacheKey, HashMap<String, Vec<BudgetProgress>>)>,
 }
\ No newline at end of file